@@ -0,0 +1,44 @@
+#ifndef OUTPUT_GLSL
+#define OUTPUT_GLSL
+
+// Transfer function of the swapchain images, set from `OutputTransfer` in
+// src/vulkan/surface_format.rs: 0 linear, 1 sRGB, 2 ST 2084 (PQ)
+layout (constant_id = 100) const int OUTPUT_TRANSFER = 0;
+
+// Luminance of SDR white in nits, ITU-R BT.2408
+const float SDR_WHITE_NITS = 203.0;
+
+vec3 srgbEncode(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+// Linear Rec. 709 with 1.0 as SDR white to PQ encoded Rec. 2020
+vec3 pqEncode(vec3 color) {
+    const mat3 REC709_TO_REC2020 = mat3(
+        0.6274, 0.0691, 0.0164,
+        0.3293, 0.9195, 0.0880,
+        0.0433, 0.0114, 0.8956
+    );
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+    vec3 y = clamp(REC709_TO_REC2020 * color * (SDR_WHITE_NITS / 10000.0), 0.0, 1.0);
+    vec3 ym1 = pow(y, vec3(m1));
+    return pow((c1 + c2 * ym1) / (1.0 + c3 * ym1), vec3(m2));
+}
+
+// Encodes a linear Rec. 709 color for the swapchain images
+vec3 encodeOutput(vec3 color) {
+    if (OUTPUT_TRANSFER == 1) {
+        return srgbEncode(clamp(color, 0.0, 1.0));
+    } else if (OUTPUT_TRANSFER == 2) {
+        return pqEncode(color);
+    }
+    return color;
+}
+
+#endif
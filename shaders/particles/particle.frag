@@ -0,0 +1,16 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+
+#include <output.glsl>
+
+layout (location = 0) in vec4 fragColor;
+layout (location = 1) in vec2 fragCorner;
+
+layout (location = 0) out vec4 outColor;
+
+void main() {
+    // Round sprite fading out towards its edge, premultiplied for additive blending
+    float falloff = clamp(1.0 - dot(fragCorner, fragCorner), 0.0, 1.0);
+    float alpha = fragColor.a * falloff;
+    outColor = vec4(encodeOutput(fragColor.rgb) * alpha, alpha);
+}
@@ -0,0 +1,16 @@
+#ifndef PARTICLE_GLSL
+#define PARTICLE_GLSL
+
+// Laid out like `Particle` in src/vulkan/particles.rs
+struct Particle {
+    // xyz: position, w: age in seconds
+    vec4 position;
+    // xyz: velocity, w: lifetime in seconds, 0 for slots never spawned
+    vec4 velocity;
+};
+
+bool isAlive(Particle particle) {
+    return particle.position.w < particle.velocity.w;
+}
+
+#endif
@@ -0,0 +1,47 @@
+#version 460
+#extension GL_GOOGLE_include_directive : require
+
+#include "particle.glsl"
+
+layout (set = 0, binding = 0) readonly buffer Particles {
+    Particle particles[];
+};
+
+layout (push_constant) uniform DrawPushConstants {
+    mat4 viewProjection;
+    vec4 startColor;
+    vec4 endColor;
+    float startSize;
+    float endSize;
+} draw;
+
+layout (location = 0) out vec4 fragColor;
+layout (location = 1) out vec2 fragCorner;
+
+vec2 corners[6] = vec2[](
+    vec2(-1.0, -1.0),
+    vec2(1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0)
+);
+
+void main() {
+    Particle particle = particles[gl_InstanceIndex];
+    vec2 corner = corners[gl_VertexIndex];
+    fragCorner = corner;
+    if (!isAlive(particle)) {
+        // Every corner at one point, so the quad is not rasterized
+        gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+        fragColor = vec4(0.0);
+        return;
+    }
+
+    float t = particle.position.w / particle.velocity.w;
+    float size = mix(draw.startSize, draw.endSize, t);
+    // Offset after the projection, so the quad always faces the viewer
+    vec4 center = draw.viewProjection * vec4(particle.position.xyz, 1.0);
+    gl_Position = center + vec4(corner * size * 0.5, 0.0, 0.0);
+    fragColor = mix(draw.startColor, draw.endColor, t);
+}
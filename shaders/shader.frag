@@ -1,9 +1,16 @@
 #version 460
+#extension GL_GOOGLE_include_directive : require
+
+#include <color.glsl>
+#include <output.glsl>
+
+layout (constant_id = 0) const bool GRAYSCALE = false;
 
 layout (location = 0) in vec3 fragColor;
 
 layout (location = 0) out vec4 outColor;
 
 void main() {
-    outColor = vec4(fragColor, 1.0);
+    vec3 color = GRAYSCALE ? vec3(luminance(fragColor)) : fragColor;
+    outColor = vec4(encodeOutput(color), 1.0);
 }
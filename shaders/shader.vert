@@ -1,5 +1,11 @@
 #version 460
 
+layout (push_constant) uniform ObjectPushConstants {
+    mat4 transform;
+    vec4 tint;
+    uint materialIndex;
+} object;
+
 layout (location = 0) out vec3 fragColor;
 
 vec2 positions[3] = vec2[](
@@ -15,6 +21,6 @@ vec3 colors[3] = vec3[](
 );
 
 void main() {
-    gl_Position = vec4(positions[gl_VertexIndex], 0.0, 1.0);
-    fragColor = colors[gl_VertexIndex];
+    gl_Position = object.transform * vec4(positions[gl_VertexIndex], 0.0, 1.0);
+    fragColor = colors[gl_VertexIndex] * object.tint.rgb;
 }
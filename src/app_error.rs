@@ -1,23 +1,188 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 use thiserror::Error;
+use vulkano::shader::ShaderStage;
+use vulkano::Version;
 
 #[derive(Debug, Clone, Copy)]
 pub enum QueueFamilyType {
     Graphics,
     Present,
+    Compute,
+}
+
+/// Why a physical device can not be used by the application.
+#[derive(Debug, Clone)]
+pub enum DeviceRejection {
+    ApiVersion {
+        required: Version,
+        supported: Version,
+    },
+    MissingFeatures(Vec<&'static str>),
+    MissingExtensions(Vec<&'static str>),
+    MissingQueueFamily(QueueFamilyType),
+    NoSurfaceFormats,
+    NoPresentModes,
+    QueryFailed(String),
+}
+
+impl Display for DeviceRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceRejection::ApiVersion {
+                required,
+                supported,
+            } => write!(f, "Vulkan {required} required, {supported} supported"),
+            DeviceRejection::MissingFeatures(features) => {
+                write!(f, "missing features {features:?}")
+            }
+            DeviceRejection::MissingExtensions(extensions) => {
+                write!(f, "missing extensions {extensions:?}")
+            }
+            DeviceRejection::MissingQueueFamily(queue_type) => {
+                write!(f, "no {queue_type:?} queue family")
+            }
+            DeviceRejection::NoSurfaceFormats => write!(f, "no surface formats"),
+            DeviceRejection::NoPresentModes => write!(f, "no present modes"),
+            DeviceRejection::QueryFailed(e) => write!(f, "query failed: {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedDevice {
+    pub name: String,
+    pub reasons: Vec<DeviceRejection>,
+}
+
+impl Display for RejectedDevice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.name)?;
+        for (i, reason) in self.reasons.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{reason}")?;
+        }
+        Ok(())
+    }
+}
+
+struct RejectedDevices<'a>(&'a [RejectedDevice]);
+
+impl Display for RejectedDevices<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, ", no physical devices found");
+        }
+        for rejected_device in self.0 {
+            write!(f, "\n  {rejected_device}")?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Error, Debug)]
 pub enum AppError {
-    #[error("has not required layers")]
-    RequiredLayers,
-    #[error("failed to find a suitable physical device")]
-    PhysicalDevices,
+    #[error("required layers are unavailable: {0:?}")]
+    RequiredLayers(Vec<String>),
+    #[error("failed to find a suitable physical device{}", RejectedDevices(.0))]
+    PhysicalDevices(Vec<RejectedDevice>),
     #[error("failed to get a {0:?} queue for logical device")]
     QueueForDevice(QueueFamilyType),
     #[error("no available swap chain formats")]
     SwapChainFormatUnavailable,
-    #[error("no entry point found")]
-    EntryPointNotFound,
+    #[error("no entry point `{name}` found in {shader}")]
+    EntryPointNotFound { shader: String, name: String },
+    #[error("entry point `{name}` in {shader} is a {found:?} shader, expected {expected:?}")]
+    ShaderStageMismatch {
+        shader: String,
+        name: String,
+        expected: ShaderStage,
+        found: ShaderStage,
+    },
+    #[error("can not compile {shader}:\n{diagnostics}")]
+    ShaderCompilation { shader: String, diagnostics: String },
+    #[error("can not compile {0:?} shaders")]
+    UnsupportedShaderStage(ShaderStage),
     #[error("can not find subpass {0}")]
     SubpassNotFound(u32),
+    #[error("invalid pipeline description {file}: {message}")]
+    PipelineDescription { file: String, message: String },
+    #[error("the device has no compute queue")]
+    ComputeUnavailable,
+    #[error("invalid config file {file}: {message}")]
+    ConfigFile { file: String, message: String },
+}
+
+impl AppError {
+    /// Process exit code for the error; 1 is left for errors that are not an `AppError`.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            AppError::RequiredLayers(_) => 10,
+            AppError::PhysicalDevices(_) => 11,
+            AppError::QueueForDevice(_) => 12,
+            AppError::SwapChainFormatUnavailable => 13,
+            AppError::EntryPointNotFound { .. } => 14,
+            AppError::SubpassNotFound(_) => 15,
+            AppError::ShaderStageMismatch { .. } => 16,
+            AppError::ShaderCompilation { .. } => 17,
+            AppError::UnsupportedShaderStage(_) => 18,
+            AppError::PipelineDescription { .. } => 19,
+            AppError::ComputeUnavailable => 20,
+            AppError::ConfigFile { .. } => 21,
+        }
+    }
+
+    /// What the user can do about the error, if anything.
+    pub fn hint(&self) -> Option<Cow<'static, str>> {
+        match self {
+            AppError::RequiredLayers(_) => Some(
+                "install the Vulkan SDK or your distribution's validation layers package, \
+                 or point VK_LAYER_PATH at the directory with the layer manifests"
+                    .into(),
+            ),
+            AppError::PhysicalDevices(rejected_devices) => {
+                // Only known from the devices rejected for supporting a lower version
+                let required = rejected_devices
+                    .iter()
+                    .flat_map(|rejected_device| &rejected_device.reasons)
+                    .find_map(|reason| match reason {
+                        DeviceRejection::ApiVersion { required, .. } => Some(required),
+                        _ => None,
+                    });
+                Some(match required {
+                    Some(required) => format!(
+                        "update the GPU driver; a device must support Vulkan {required}, \
+                         presentation to the window surface and the khr_swapchain extension"
+                    )
+                    .into(),
+                    None => "update the GPU driver; a device must support presentation to the \
+                             window surface and the khr_swapchain extension"
+                        .into(),
+                })
+            }
+            AppError::SwapChainFormatUnavailable => Some(
+                "the window surface reports no formats, try another display server or driver"
+                    .into(),
+            ),
+            AppError::EntryPointNotFound { .. } => {
+                Some("pass the name of the shader entry point with --shader-entry-point".into())
+            }
+            AppError::ShaderStageMismatch { .. } => {
+                Some("check that --vertex-shader and --fragment-shader are not swapped".into())
+            }
+            AppError::PipelineDescription { .. } => {
+                Some("pipelines/triangle.toml in the source tree documents the format".into())
+            }
+            AppError::ConfigFile { .. } => {
+                Some("vulkt.example.toml in the source tree documents the format".into())
+            }
+            AppError::QueueForDevice(_)
+            | AppError::SubpassNotFound(_)
+            | AppError::ShaderCompilation { .. }
+            | AppError::UnsupportedShaderStage(_)
+            | AppError::ComputeUnavailable => None,
+        }
+    }
 }
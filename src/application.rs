@@ -1,101 +1,306 @@
-use crate::vulkan::AppVulkan;
-use crate::window::AppWindow;
+use crate::camera::{Camera, CameraController, FlyController, OrbitController};
+use crate::config::{AppConfig, CameraControl};
+use crate::config_file::{ConfigFile, DEFAULT_CONFIG_FILE};
+use crate::fixed_timestep::FixedTimestep;
+use crate::frame_limiter::FrameLimiter;
+use crate::frame_stats::FrameStats;
+use crate::input::{Input, TOGGLE_FULLSCREEN};
+use crate::particles::{EmitterConfig, EmitterId};
+use crate::vulkan::{AppVulkan, ObjectPushConstants, Specialization};
+use crate::window::{set_fullscreen, AppWindow};
 use anyhow::Result;
+use glam::Vec3;
+use smallvec::SmallVec;
+use std::path::Path;
 use std::sync::Arc;
-use vulkano::device::physical::PhysicalDevice;
-use vulkano::device::{Device, Queue};
-use vulkano::image::view::ImageView;
-use vulkano::image::Image;
-use vulkano::instance::debug::DebugUtilsMessenger;
-use vulkano::instance::Instance;
-use vulkano::pipeline::{GraphicsPipeline, PipelineLayout};
-use vulkano::render_pass::{Framebuffer, RenderPass};
-use vulkano::swapchain::{Surface, Swapchain};
+use std::time::Instant;
+use tracing::{error, info, warn};
+use vulkano::buffer::BufferContents;
+use vulkano::descriptor_set::WriteDescriptorSet;
+use vulkano::device::Device;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::swapchain::PresentMode;
+use vulkano::sync::Sharing;
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::EventLoop;
+use winit::platform::run_return::EventLoopExtRunReturn;
 use winit::window::Window;
 
 pub struct Application {
-    event_loop: EventLoop<()>,
+    config: AppConfig,
+    /// `None` without a frame rate cap or with a present mode synchronized with the display
+    frame_limiter: Option<FrameLimiter>,
+    fixed_timestep: FixedTimestep,
+    frame_stats: FrameStats,
+    input: Input,
+    /// `None` to draw in clip space
+    camera: Option<Camera>,
+    /// The camera before the last update step, rendered frames interpolate from it to `camera`
+    previous_camera: Option<Camera>,
+    camera_controller: Option<Box<dyn CameraController>>,
+    vulkan: AppVulkan,
     window: Arc<Window>,
-    _instance: Arc<Instance>,
-    _debug_utils_messenger: Option<DebugUtilsMessenger>,
-    _surface: Arc<Surface>,
-    _physical_device: Arc<PhysicalDevice>,
-    _device: Arc<Device>,
-    _graphics_queue: Arc<Queue>,
-    _present_queue: Arc<Queue>,
-    _swapchain: Arc<Swapchain>,
-    _swapchain_images: Vec<Arc<Image>>,
-    _swapchain_image_views: Vec<Arc<ImageView>>,
-    _render_pass: Arc<RenderPass>,
-    _pipeline_layout: Arc<PipelineLayout>,
-    _graphics_pipeline: Arc<GraphicsPipeline>,
-    _framebuffers: Vec<Arc<Framebuffer>>,
+    /// Taken out while the loop runs, so the event handler can borrow the rest of the application
+    event_loop: Option<EventLoop<()>>,
 }
 
 impl Application {
-    pub fn new(enable_validation: bool) -> Result<Self> {
-        let AppWindow { event_loop, window } = AppWindow::init()?;
+    pub fn new(config: &AppConfig) -> Result<Self> {
+        let AppWindow { event_loop, window } = AppWindow::init(&config.window)?;
         let window = Arc::new(window);
-        let AppVulkan {
-            instance,
-            debug_utils_messenger,
-            surface,
-            physical_device,
-            device,
-            graphics_queue,
-            present_queue,
-            swapchain,
-            swapchain_images,
-            swapchain_image_views,
-            render_pass,
-            pipeline_layout,
-            graphics_pipeline,
-            framebuffers,
-        } = AppVulkan::init(&event_loop, &window, enable_validation)?;
+        let mut vulkan = AppVulkan::init(&event_loop, &window, config)?;
+        if config.particles {
+            let emitter = match config.camera {
+                Some(_) => EmitterConfig::default().flipped_y(),
+                None => EmitterConfig::default(),
+            };
+            vulkan.add_emitter(emitter)?;
+        }
+
+        let camera = config.camera.map(|_| Camera::default());
+        let camera_controller = camera.as_ref().zip(config.camera).map(
+            |(camera, control)| -> Box<dyn CameraController> {
+                match control {
+                    CameraControl::Fly => Box::new(FlyController::new(camera)),
+                    CameraControl::Orbit => Box::new(OrbitController::new(camera, Vec3::ZERO)),
+                }
+            },
+        );
+
+        let frame_limiter = config.max_fps.and_then(|max_fps| {
+            let present_mode = vulkan.swapchain.present_mode();
+            if matches!(present_mode, PresentMode::Fifo | PresentMode::FifoRelaxed) {
+                info!(
+                    "ignoring frame rate cap, present mode {present_mode:?} waits for the display"
+                );
+                None
+            } else {
+                info!("frame rate capped at {max_fps} fps");
+                Some(FrameLimiter::new(max_fps))
+            }
+        });
 
         Ok(Self {
-            event_loop,
+            config: config.clone(),
+            frame_limiter,
+            fixed_timestep: FixedTimestep::new(config.update_rate),
+            frame_stats: FrameStats::new(Instant::now()),
+            input: Input::new(config.input.clone()),
+            camera,
+            previous_camera: camera,
+            camera_controller,
+            vulkan,
             window,
-            _instance: instance,
-            _debug_utils_messenger: debug_utils_messenger,
-            _surface: surface,
-            _physical_device: physical_device,
-            _device: device,
-            _graphics_queue: graphics_queue,
-            _present_queue: present_queue,
-            _swapchain: swapchain,
-            _swapchain_images: swapchain_images,
-            _swapchain_image_views: swapchain_image_views,
-            _render_pass: render_pass,
-            _pipeline_layout: pipeline_layout,
-            _graphics_pipeline: graphics_pipeline,
-            _framebuffers: framebuffers,
+            event_loop: Some(event_loop),
         })
     }
 
+    /// Keyboard and mouse state; actions can be rebound through `Input::map`.
+    #[inline]
+    pub fn input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    /// The camera the scene is viewed through, `None` when drawing in clip space.
+    #[inline]
+    pub fn camera_mut(&mut self) -> &mut Option<Camera> {
+        &mut self.camera
+    }
+
+    /// Replaces what moves the camera every update step; `None` leaves it where it is.
+    pub fn set_camera_controller(&mut self, controller: Option<Box<dyn CameraController>>) {
+        self.camera_controller = controller;
+    }
+
+    /// Sets the transform, tint and material index pushed to the shaders with every draw.
+    ///
+    /// The transform places the object in the world seen by the camera, or in clip space when
+    /// there is no camera.
+    pub fn set_object_push_constants(&mut self, push_constants: ObjectPushConstants) {
+        self.vulkan.object_push_constants = push_constants;
+    }
+
+    /// Frame rate, update rate and frame times, summarized every second.
+    #[inline]
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// Physical pixels per logical pixel of the monitor the window is on, for sizing UI and text.
+    #[inline]
+    pub fn scale_factor(&self) -> f64 {
+        self.window.scale_factor()
+    }
+
+    /// The logical device; `Device::enabled_features` and `Device::enabled_extensions` tell which
+    /// optional capabilities it was created with.
     #[inline]
-    pub fn run(self) -> ! {
+    pub fn device(&self) -> &Arc<Device> {
+        &self.vulkan.device
+    }
+
+    /// Sharing mode for buffers and images used by both compute and graphics work.
+    pub fn compute_sharing(&self) -> Result<Sharing<SmallVec<[u32; 4]>>> {
+        Ok(self.vulkan.compute()?.sharing())
+    }
+
+    /// Builds a compute pipeline from a SPIR-V (`.spv`), HLSL (`.hlsl`) or GLSL shader file.
+    pub fn compute_pipeline(
+        &self,
+        path: &Path,
+        entry_point_name: &str,
+    ) -> Result<Arc<ComputePipeline>> {
+        self.vulkan
+            .compute()?
+            .load_pipeline(path, entry_point_name, &Specialization::new())
+    }
+
+    /// Dispatches compute work, ordered after the frames drawn so far and before the next one.
+    ///
+    /// `push_constants` are ignored if the shader declares none.
+    pub fn dispatch<Pc: BufferContents>(
+        &mut self,
+        pipeline: &Arc<ComputePipeline>,
+        descriptor_writes: impl IntoIterator<Item = WriteDescriptorSet>,
+        push_constants: Pc,
+        group_counts: [u32; 3],
+    ) -> Result<()> {
+        self.vulkan
+            .dispatch(pipeline, descriptor_writes, push_constants, group_counts)
+    }
+
+    /// Adds a particle emitter, simulated with compute shaders and drawn over the scene.
+    pub fn add_emitter(&mut self, config: EmitterConfig) -> Result<EmitterId> {
+        self.vulkan.add_emitter(config)
+    }
+
+    /// Replaces the config of an emitter; particles are respawned if the capacity changes.
+    pub fn set_emitter(&mut self, id: EmitterId, config: EmitterConfig) -> Result<()> {
+        self.vulkan.set_emitter(id, config)
+    }
+
+    /// Removes an emitter with its particles, returning whether it existed.
+    pub fn remove_emitter(&mut self, id: EmitterId) -> bool {
+        self.vulkan.remove_emitter(id)
+    }
+
+    #[inline]
+    pub fn run(self) -> Result<()> {
         self.main_loop()
     }
 
-    fn main_loop(self) -> ! {
-        self.event_loop.run(move |event, _, control_flow| {
-            control_flow.set_poll();
-            match event {
-                Event::WindowEvent {
-                    event: WindowEvent::CloseRequested,
-                    ..
-                } => {
-                    control_flow.set_exit();
+    fn main_loop(mut self) -> Result<()> {
+        let mut result = Ok(());
+        let mut event_loop = self.event_loop.take().expect("main loop runs once");
+        event_loop.run_return(|event, _, control_flow| match event {
+            Event::NewEvents(_) => {
+                control_flow.set_poll();
+            }
+            Event::WindowEvent { event, .. } => {
+                self.input.handle_window_event(&event);
+                match event {
+                    WindowEvent::CloseRequested => control_flow.set_exit(),
+                    WindowEvent::Resized(_) => self.vulkan.swapchain_outdated = true,
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => {
+                        // Keeping the suggested size keeps the logical size of the window
+                        info!(
+                            "scale factor changed to {scale_factor}, \
+                                 inner size {new_inner_size:?}"
+                        );
+                        self.vulkan.swapchain_outdated = true;
+                    }
+                    _ => {}
                 }
-                Event::MainEventsCleared => {
-                    self.window.request_redraw();
+            }
+            Event::DeviceEvent { event, .. } => {
+                self.input.handle_device_event(&event);
+            }
+            Event::MainEventsCleared => {
+                let steps = self.fixed_timestep.advance(Instant::now());
+                for _ in 0..steps {
+                    self.update(self.fixed_timestep.delta_time());
+                }
+                self.frame_stats.record_updates(steps);
+                self.vulkan.reload_changed_shaders(&self.config);
+                let next_frame = self
+                    .frame_limiter
+                    .as_mut()
+                    .and_then(|frame_limiter| frame_limiter.poll(Instant::now()));
+                match next_frame {
+                    Some(next_frame) => control_flow.set_wait_until(next_frame),
+                    None => self.window.request_redraw(),
                 }
-                Event::RedrawRequested(_) => {}
-                _ => {}
             }
-        })
+            Event::RedrawRequested(_) => {
+                if let Err(e) = self.render(self.fixed_timestep.alpha()) {
+                    result = Err(e.context("can not draw frame"));
+                    control_flow.set_exit();
+                }
+            }
+            _ => {}
+        });
+        info!("event loop exited, waiting for device to become idle");
+        let idle = self.vulkan.wait_idle();
+        match (result, idle) {
+            (Err(e), Err(idle_error)) => {
+                // The error that ended the loop is the one to report, it likely caused this one
+                error!("can not wait for device to become idle: {idle_error:?}");
+                Err(e)
+            }
+            (result, idle) => result.and(idle),
+        }
+    }
+
+    /// Advances the simulation by a fixed step of `delta_time` seconds.
+    ///
+    /// Input received since the last step is seen by the next one only.
+    fn update(&mut self, delta_time: f32) {
+        if self.input.was_activated(TOGGLE_FULLSCREEN) {
+            let window_config = &mut self.config.window;
+            window_config.fullscreen = window_config.fullscreen.next();
+            set_fullscreen(
+                &self.window,
+                window_config.fullscreen,
+                window_config.video_mode,
+            );
+            self.vulkan.swapchain_outdated = true;
+            // Without a config file, one is created where the next start looks for it
+            let config_file = self
+                .config
+                .config_file
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_CONFIG_FILE));
+            if let Err(e) = ConfigFile::save_fullscreen(config_file, window_config.fullscreen) {
+                warn!("can not save fullscreen mode: {e:?}");
+            }
+        }
+        if let Some(camera) = &mut self.camera {
+            self.previous_camera = Some(*camera);
+            if let Some(controller) = &mut self.camera_controller {
+                controller.update(camera, &self.input, delta_time);
+            }
+        }
+        self.vulkan.step_particles(delta_time);
+        self.input.end_frame();
+    }
+
+    /// Draws a frame `alpha` of the way from the state before the last update step to the state
+    /// after it.
+    fn render(&mut self, alpha: f32) -> Result<()> {
+        if let Some(camera) = &self.camera {
+            let camera = self.previous_camera.map_or(*camera, |previous_camera| {
+                previous_camera.lerp(camera, alpha)
+            });
+            let [width, height] = self.vulkan.swapchain.image_extent();
+            let aspect_ratio = width as f32 / height.max(1) as f32;
+            self.vulkan
+                .set_view_projection(camera.view_projection(aspect_ratio));
+        }
+        self.vulkan.draw_frame(&self.window, &self.config)?;
+        self.frame_stats.record_frame(Instant::now());
+        Ok(())
     }
 }
@@ -0,0 +1,422 @@
+use crate::input::{
+    Input, LOOK, MOVE_BACKWARD, MOVE_DOWN, MOVE_FORWARD, MOVE_LEFT, MOVE_RIGHT, MOVE_UP,
+};
+use glam::{EulerRot, Mat4, Quat, Vec3};
+use std::f32::consts::FRAC_PI_2;
+
+/// Pitch is kept short of straight up or down, where yaw is undefined.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// Maps view space, right-handed with +Y up and the camera looking down -Z, to Vulkan clip
+/// space, where +Y points down and depth goes from 0 at the near plane to 1 at the far plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in radians
+        fov_y: f32,
+        near: f32,
+        far: f32,
+    },
+    Orthographic {
+        /// Height of the view volume in world units, the width follows the aspect ratio
+        height: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect_ratio: f32) -> Mat4 {
+        // glam's `_rh` projections already map depth to 0..1, only Y has to be flipped
+        let matrix = match *self {
+            Projection::Perspective { fov_y, near, far } => {
+                Mat4::perspective_rh(fov_y, aspect_ratio, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        };
+        Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0)) * matrix
+    }
+}
+
+/// A viewpoint in world space, with +Y up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    /// Rotation from looking down -Z with +Y up
+    pub rotation: Quat,
+    pub projection: Projection,
+}
+
+impl Default for Camera {
+    /// Two units in front of the origin, looking at it with a 60° field of view.
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0.0, 0.0, 2.0),
+            rotation: Quat::IDENTITY,
+            projection: Projection::Perspective {
+                fov_y: 60f32.to_radians(),
+                near: 0.1,
+                far: 100.0,
+            },
+        }
+    }
+}
+
+impl Camera {
+    /// Turns the camera towards `target`, keeping +Y up.
+    pub fn look_at(&mut self, target: Vec3) {
+        let view = Mat4::look_at_rh(self.position, target, Vec3::Y);
+        self.rotation = Quat::from_mat4(&view.inverse());
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    /// Maps world space to view space.
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+    }
+
+    /// The camera `alpha` of the way from this one to `next`, with the projection of `next`.
+    pub fn lerp(&self, next: &Camera, alpha: f32) -> Camera {
+        Camera {
+            position: self.position.lerp(next.position, alpha),
+            rotation: self.rotation.slerp(next.rotation, alpha),
+            projection: next.projection,
+        }
+    }
+
+    /// Maps world space to Vulkan clip space, for a viewport of `aspect_ratio` (width / height).
+    pub fn view_projection(&self, aspect_ratio: f32) -> Mat4 {
+        self.projection.matrix(aspect_ratio) * self.view()
+    }
+}
+
+/// Moves a camera from user input.
+pub trait CameraController {
+    /// Applies the input since the last frame, `delta_time` seconds ago, to `camera`.
+    fn update(&mut self, camera: &mut Camera, input: &Input, delta_time: f32);
+}
+
+/// Flies freely: the move actions move along the view direction, dragging with the look action
+/// held turns the camera.
+#[derive(Debug, Clone)]
+pub struct FlyController {
+    /// World units per second
+    pub speed: f32,
+    /// Radians per unit of mouse movement
+    pub sensitivity: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl FlyController {
+    /// A controller continuing from the orientation of `camera`.
+    pub fn new(camera: &Camera) -> Self {
+        let (yaw, pitch, _) = camera.rotation.to_euler(EulerRot::YXZ);
+        Self {
+            speed: 2.0,
+            sensitivity: 0.003,
+            yaw,
+            pitch: pitch.clamp(-MAX_PITCH, MAX_PITCH),
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, camera: &mut Camera, input: &Input, delta_time: f32) {
+        if input.is_active(LOOK) {
+            let [dx, dy] = input.mouse_delta();
+            self.yaw -= dx as f32 * self.sensitivity;
+            self.pitch = (self.pitch - dy as f32 * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        camera.rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0);
+
+        let direction = camera.forward() * input.axis(MOVE_BACKWARD, MOVE_FORWARD)
+            + camera.right() * input.axis(MOVE_LEFT, MOVE_RIGHT)
+            + Vec3::Y * input.axis(MOVE_DOWN, MOVE_UP);
+        camera.position += direction.normalize_or_zero() * self.speed * delta_time;
+    }
+}
+
+/// Circles around a target: dragging with the look action held or the left, right, up and down
+/// move actions orbit, the wheel and the forward and backward move actions zoom.
+#[derive(Debug, Clone)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    /// Radians per unit of mouse movement
+    pub sensitivity: f32,
+    /// Radians per second when orbiting with the keyboard
+    pub angular_speed: f32,
+    /// Fraction of the distance zoomed per wheel line; a held key zooms `KEY_ZOOM_RATE` lines per
+    /// second
+    pub zoom_speed: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl OrbitController {
+    /// Closest the camera gets to the target.
+    const MIN_DISTANCE: f32 = 0.05;
+    /// Wheel lines per second the forward and backward move actions zoom by.
+    pub const KEY_ZOOM_RATE: f32 = 5.0;
+
+    /// A controller orbiting `target` from where `camera` is.
+    pub fn new(camera: &Camera, target: Vec3) -> Self {
+        let offset = camera.position - target;
+        let distance = offset.length().max(Self::MIN_DISTANCE);
+        let direction = offset.try_normalize().unwrap_or(Vec3::Z);
+        Self {
+            target,
+            distance,
+            sensitivity: 0.005,
+            angular_speed: 1.5,
+            zoom_speed: 0.1,
+            yaw: direction.x.atan2(direction.z),
+            pitch: (-direction.y).asin().clamp(-MAX_PITCH, MAX_PITCH),
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut Camera, input: &Input, delta_time: f32) {
+        // Dragging turns the scene with the mouse, the keyboard moves the camera
+        if input.is_active(LOOK) {
+            let [dx, dy] = input.mouse_delta();
+            self.yaw -= dx as f32 * self.sensitivity;
+            self.pitch -= dy as f32 * self.sensitivity;
+        }
+        let angle = self.angular_speed * delta_time;
+        self.yaw += input.axis(MOVE_LEFT, MOVE_RIGHT) * angle;
+        self.pitch -= input.axis(MOVE_DOWN, MOVE_UP) * angle;
+        self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+
+        let zoom = input.wheel_delta()[1] as f32
+            + input.axis(MOVE_BACKWARD, MOVE_FORWARD) * Self::KEY_ZOOM_RATE * delta_time;
+        self.distance =
+            (self.distance * (1.0 - self.zoom_speed).powf(zoom)).max(Self::MIN_DISTANCE);
+
+        // The camera looks down its -Z axis, so it sits on its +Z axis from the target
+        camera.rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.0);
+        camera.position = self.target + camera.rotation * Vec3::Z * self.distance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputMap;
+    use winit::event::{
+        DeviceEvent, DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton,
+        MouseScrollDelta, TouchPhase, VirtualKeyCode, WindowEvent,
+    };
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-4),
+            "{actual} is not {expected}"
+        );
+    }
+
+    fn device_id() -> DeviceId {
+        // SAFETY: `Input` ignores the ID, it never reaches winit
+        unsafe { DeviceId::dummy() }
+    }
+
+    #[allow(deprecated)]
+    fn press_key(input: &mut Input, key: VirtualKeyCode) {
+        input.handle_window_event(&WindowEvent::KeyboardInput {
+            device_id: device_id(),
+            input: KeyboardInput {
+                scancode: 0,
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        });
+    }
+
+    #[allow(deprecated)]
+    fn drag(input: &mut Input, dx: f64, dy: f64) {
+        input.handle_window_event(&WindowEvent::MouseInput {
+            device_id: device_id(),
+            state: ElementState::Pressed,
+            button: MouseButton::Right,
+            modifiers: ModifiersState::empty(),
+        });
+        input.handle_device_event(&DeviceEvent::MouseMotion { delta: (dx, dy) });
+    }
+
+    /// Up, right and forward of `camera`, checking that they are orthonormal and level.
+    fn basis(camera: &Camera) -> (Vec3, Vec3, Vec3) {
+        let (forward, right) = (camera.forward(), camera.right());
+        let up = right.cross(forward);
+        assert!((forward.length() - 1.0).abs() < 1e-4);
+        assert!((right.length() - 1.0).abs() < 1e-4);
+        assert!(forward.dot(right).abs() < 1e-4);
+        // No roll: right stays horizontal and up points to the sky
+        assert!(right.y.abs() < 1e-4, "right {right} is not level");
+        assert!(up.y > 0.0, "up {up} points down");
+        (up, right, forward)
+    }
+
+    #[test]
+    fn world_up_maps_to_negative_clip_y() {
+        let camera = Camera::default();
+        let view_projection = camera.view_projection(16.0 / 9.0);
+        let center = view_projection.project_point3(Vec3::ZERO);
+        assert_close(center * Vec3::new(1.0, 1.0, 0.0), Vec3::ZERO);
+        assert!(center.z > 0.0 && center.z < 1.0, "{center}");
+        let up = view_projection.project_point3(Vec3::Y * 0.5);
+        assert!(up.y < 0.0, "{up}");
+        let right = view_projection.project_point3(Vec3::X * 0.5);
+        assert!(right.x > 0.0, "{right}");
+    }
+
+    #[test]
+    fn near_and_far_planes_map_to_depth_0_and_1() {
+        for projection in [
+            Projection::Perspective {
+                fov_y: FRAC_PI_2,
+                near: 0.5,
+                far: 50.0,
+            },
+            Projection::Orthographic {
+                height: 4.0,
+                near: 0.5,
+                far: 50.0,
+            },
+        ] {
+            let matrix = projection.matrix(2.0);
+            assert_close(matrix.project_point3(Vec3::new(0.0, 0.0, -0.5)), Vec3::ZERO);
+            assert_close(matrix.project_point3(Vec3::new(0.0, 0.0, -50.0)), Vec3::Z);
+        }
+    }
+
+    #[test]
+    fn projections_fill_the_viewport() {
+        let perspective = Projection::Perspective {
+            fov_y: FRAC_PI_2,
+            near: 0.5,
+            far: 50.0,
+        };
+        // A 90° field of view sees as far up as it looks ahead
+        assert_close(
+            perspective
+                .matrix(2.0)
+                .project_point3(Vec3::new(2.0, 1.0, -1.0)),
+            Vec3::new(
+                1.0,
+                -1.0,
+                perspective.matrix(2.0).project_point3(Vec3::NEG_Z).z,
+            ),
+        );
+        let orthographic = Projection::Orthographic {
+            height: 4.0,
+            near: 0.5,
+            far: 50.0,
+        };
+        let corner = orthographic
+            .matrix(2.0)
+            .project_point3(Vec3::new(4.0, 2.0, -0.5));
+        assert_close(corner, Vec3::new(1.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn look_at_faces_the_target() {
+        let mut camera = Camera {
+            position: Vec3::new(3.0, 2.0, -1.0),
+            ..Camera::default()
+        };
+        camera.look_at(Vec3::ZERO);
+        let (_, _, forward) = basis(&camera);
+        assert_close(forward, -camera.position.normalize());
+        let target = camera.view_projection(1.0).project_point3(Vec3::ZERO);
+        assert_close(target * Vec3::new(1.0, 1.0, 0.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn fly_controller_turns_and_moves_along_its_basis() {
+        let mut camera = Camera {
+            position: Vec3::new(2.0, 1.0, 2.0),
+            ..Camera::default()
+        };
+        camera.look_at(Vec3::ZERO);
+        let mut controller = FlyController::new(&camera);
+        let mut input = Input::new(InputMap::default());
+
+        // Without input the orientation is kept
+        let (_, right, forward) = basis(&camera);
+        controller.update(&mut camera, &input, 0.1);
+        let (_, new_right, new_forward) = basis(&camera);
+        assert_close(new_forward, forward);
+        assert_close(new_right, right);
+
+        let position = camera.position;
+        press_key(&mut input, VirtualKeyCode::W);
+        controller.update(&mut camera, &input, 0.5);
+        assert_close(camera.position, position + forward * controller.speed * 0.5);
+
+        // Dragging right turns right, keeping the camera level
+        input.end_frame();
+        drag(&mut input, 100.0, 0.0);
+        controller.update(&mut camera, &input, 0.0);
+        let (_, _, turned) = basis(&camera);
+        assert!(turned.dot(right) > 0.0, "{turned} turned away from {right}");
+    }
+
+    #[test]
+    fn orbit_controller_faces_the_target() {
+        let target = Vec3::new(1.0, 0.0, -1.0);
+        let mut camera = Camera {
+            position: target + Vec3::new(1.0, 1.0, 1.0),
+            ..Camera::default()
+        };
+        let mut controller = OrbitController::new(&camera, target);
+        let mut input = Input::new(InputMap::default());
+
+        // Without input the camera stays where it is, looking at the target
+        controller.update(&mut camera, &input, 0.1);
+        assert_close(camera.position, target + Vec3::new(1.0, 1.0, 1.0));
+        let (_, _, forward) = basis(&camera);
+        assert_close(forward, Vec3::new(-1.0, -1.0, -1.0).normalize());
+
+        // Orbiting keeps the distance and the view on the target
+        press_key(&mut input, VirtualKeyCode::D);
+        drag(&mut input, 30.0, -20.0);
+        controller.update(&mut camera, &input, 0.5);
+        let (_, _, forward) = basis(&camera);
+        assert!((camera.position.distance(target) - 3f32.sqrt()).abs() < 1e-4);
+        assert_close(forward, (target - camera.position).normalize());
+
+        // Zooming in moves towards the target
+        input.end_frame();
+        input.handle_window_event(&WindowEvent::MouseWheel {
+            device_id: device_id(),
+            delta: MouseScrollDelta::LineDelta(0.0, 1.0),
+            phase: TouchPhase::Moved,
+            modifiers: ModifiersState::empty(),
+        });
+        let position = camera.position;
+        controller.update(&mut camera, &input, 0.0);
+        assert_close(camera.position, target + (position - target) * 0.9);
+    }
+}
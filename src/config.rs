@@ -0,0 +1,295 @@
+use crate::input::InputMap;
+use clap::ValueEnum;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Default)]
+pub struct AppConfig {
+    /// Enable validation layer and debug utils messenger
+    pub enable_validation: bool,
+    /// Additional instance layers, enabled regardless of validation
+    pub layers: Vec<String>,
+    /// Vertex shader file to use instead of the embedded one
+    pub vertex_shader: Option<PathBuf>,
+    /// Fragment shader file to use instead of the embedded one
+    pub fragment_shader: Option<PathBuf>,
+    /// Entry point of shader files, `main` if not set
+    pub shader_entry_point: Option<String>,
+    /// Directory searched by `#include` in shader sources, the embedded shaders' one if not set
+    pub shader_root: Option<PathBuf>,
+    /// Pipeline description file to use instead of the built-in one
+    pub pipeline: Option<PathBuf>,
+    /// Variant of the pipeline description to draw with, the shared constants if not set
+    pub pipeline_variant: Option<String>,
+    /// Render without render pass objects if the device supports dynamic rendering
+    pub dynamic_rendering: bool,
+    /// Add a particle emitter with the default config
+    pub particles: bool,
+    /// Rebuild the graphics pipeline when files the pipeline is built from change
+    pub hot_reload: bool,
+    pub present_mode: PresentModePreference,
+    /// Frame rate cap for present modes not synchronized with the display
+    pub max_fps: Option<u32>,
+    /// Fixed update steps per second, independent of the frame rate
+    pub update_rate: u32,
+    pub surface_format: SurfaceFormatPreference,
+    /// Number of swapchain images, overriding the count `frame_pacing` calls for
+    pub swapchain_images: Option<u32>,
+    pub frame_pacing: FramePacing,
+    pub window: WindowConfig,
+    /// Config file that was read, `None` if none was; the fullscreen mode is saved to it when
+    /// toggled, or to a new `vulkt.toml` in the working directory
+    pub config_file: Option<PathBuf>,
+    pub input: InputMap,
+    /// Camera controller driven by the input, no camera if not set
+    pub camera: Option<CameraControl>,
+}
+
+/// Present mode requested for the swapchain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PresentModePreference {
+    /// Waits for vertical blank, never tears
+    Fifo,
+    /// Like fifo, but tears when a frame is late instead of waiting for the next vertical blank
+    FifoRelaxed,
+    /// Replaces the queued frame with newer ones, low latency without tearing
+    Mailbox,
+    /// Presents right away, may tear
+    Immediate,
+    /// Mailbox if available, fifo otherwise
+    #[default]
+    Auto,
+}
+
+/// Format and color space requested for the swapchain images.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SurfaceFormatPreference {
+    /// 8-bit sRGB
+    Srgb,
+    /// 10-bit sRGB, less banding in gradients
+    #[value(name = "10bit")]
+    TenBit,
+    /// HDR10: Rec. 2020 primaries with the ST 2084 (PQ) transfer function
+    Hdr10,
+    /// Extended linear sRGB in 16-bit floats, values above 1.0 are brighter than SDR white
+    #[value(name = "scrgb")]
+    ScRgb,
+    /// 8-bit sRGB, the format every display supports
+    #[default]
+    Auto,
+}
+
+/// Trade-off picking the number of swapchain images, unless it is set explicitly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FramePacing {
+    /// Double buffering: fewer frames queued, less input latency
+    Latency,
+    /// Triple buffering: the GPU is kept busy while the display holds a frame
+    #[default]
+    Throughput,
+}
+
+/// How the camera is moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CameraControl {
+    /// Move with the move actions, look around dragging with the look action held
+    Fly,
+    /// Orbit the origin dragging with the look action held, zoom with the wheel
+    Orbit,
+}
+
+/// Settings of the main window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowConfig {
+    /// Inner width in logical pixels, scaled by the scale factor of the monitor
+    pub width: u32,
+    /// Inner height in logical pixels, scaled by the scale factor of the monitor
+    pub height: u32,
+    pub title: String,
+    pub resizable: bool,
+    pub maximized: bool,
+    pub decorations: bool,
+    /// Outer position in physical pixels, chosen by the window manager if not set
+    pub position: Option<[i32; 2]>,
+    pub fullscreen: FullscreenMode,
+    /// Video mode for exclusive fullscreen, the largest one of the monitor if not set
+    pub video_mode: Option<VideoModeRequest>,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            title: "Vulkan Tutorial".to_string(),
+            resizable: false,
+            maximized: false,
+            decorations: true,
+            position: None,
+            fullscreen: FullscreenMode::Windowed,
+            video_mode: None,
+        }
+    }
+}
+
+impl WindowConfig {
+    /// Replaces the settings `overrides` sets.
+    pub fn apply(&mut self, overrides: &WindowOverrides) {
+        let WindowOverrides {
+            width,
+            height,
+            title,
+            resizable,
+            maximized,
+            decorations,
+            position,
+            fullscreen,
+            video_mode,
+        } = overrides.clone();
+        self.width = width.unwrap_or(self.width);
+        self.height = height.unwrap_or(self.height);
+        self.title = title.unwrap_or(std::mem::take(&mut self.title));
+        self.resizable = resizable.unwrap_or(self.resizable);
+        self.maximized = maximized.unwrap_or(self.maximized);
+        self.decorations = decorations.unwrap_or(self.decorations);
+        self.position = position.or(self.position);
+        self.fullscreen = fullscreen.unwrap_or(self.fullscreen);
+        self.video_mode = video_mode.or(self.video_mode);
+    }
+}
+
+/// Window settings from one source: command line, environment or config file, `None` where the
+/// source does not set them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowOverrides {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub title: Option<String>,
+    pub resizable: Option<bool>,
+    pub maximized: Option<bool>,
+    pub decorations: Option<bool>,
+    pub position: Option<[i32; 2]>,
+    pub fullscreen: Option<FullscreenMode>,
+    pub video_mode: Option<VideoModeRequest>,
+}
+
+/// How the window covers the screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    /// A borderless window covering the monitor it is on, at the desktop video mode
+    Borderless,
+    /// Exclusive use of the monitor, switching it to the requested video mode
+    Exclusive,
+}
+
+impl FullscreenMode {
+    /// The mode after this one when toggling: windowed, borderless, exclusive, then windowed.
+    pub fn next(self) -> Self {
+        match self {
+            FullscreenMode::Windowed => FullscreenMode::Borderless,
+            FullscreenMode::Borderless => FullscreenMode::Exclusive,
+            FullscreenMode::Exclusive => FullscreenMode::Windowed,
+        }
+    }
+}
+
+/// Resolution and refresh rate of an exclusive fullscreen video mode, written as
+/// `WIDTHxHEIGHT` or `WIDTHxHEIGHT@HZ`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoModeRequest {
+    pub width: u32,
+    pub height: u32,
+    /// In hertz, the highest one available if not set
+    pub refresh_rate: Option<u32>,
+}
+
+impl FromStr for VideoModeRequest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("expected WIDTHxHEIGHT or WIDTHxHEIGHT@HZ, got `{s}`");
+        let (size, refresh_rate) = match s.split_once('@') {
+            Some((size, refresh_rate)) => (size, Some(refresh_rate)),
+            None => (s, None),
+        };
+        let (width, height) = size.split_once('x').ok_or_else(error)?;
+        let number = |n: &str| n.trim().parse::<u32>().ok().filter(|&n| n > 0);
+        Ok(Self {
+            width: number(width).ok_or_else(error)?,
+            height: number(height).ok_or_else(error)?,
+            refresh_rate: refresh_rate
+                .map(|refresh_rate| number(refresh_rate).ok_or_else(error))
+                .transpose()?,
+        })
+    }
+}
+
+impl Display for VideoModeRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)?;
+        if let Some(refresh_rate) = self.refresh_rate {
+            write!(f, "@{refresh_rate}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<VideoModeRequest, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn parses_video_modes() {
+        assert_eq!(
+            parse("1920x1080"),
+            Ok(VideoModeRequest {
+                width: 1920,
+                height: 1080,
+                refresh_rate: None,
+            })
+        );
+        assert_eq!(
+            parse("2560x1440@144"),
+            Ok(VideoModeRequest {
+                width: 2560,
+                height: 1440,
+                refresh_rate: Some(144),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_video_modes() {
+        for s in [
+            "",
+            "1920",
+            "1920x",
+            "x1080",
+            "0x1080",
+            "1920x0",
+            "1920x1080@",
+            "1920x1080@0",
+            "1920*1080",
+            "-1x1080",
+            "1920x1080@60@75",
+            "axb",
+        ] {
+            let error = parse(s).unwrap_err();
+            assert!(error.contains(&format!("got `{s}`")), "{s}: {error}");
+        }
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for s in ["800x600", "3840x2160@60"] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use crate::app_error::AppError;
+use crate::config::{FullscreenMode, WindowOverrides};
+use crate::input::{Binding, ACTIONS};
+use crate::toml_section::{parse_document, Section};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml_edit::{table, value};
+use tracing::info;
+
+/// Config file read from the working directory if no other one is given.
+pub const DEFAULT_CONFIG_FILE: &str = "vulkt.toml";
+
+/// Settings read from a config file, overridden by environment variables and command line
+/// arguments.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    /// `None` if no config file was read
+    pub path: Option<PathBuf>,
+    pub window: WindowOverrides,
+    /// Bindings of the actions the file rebinds
+    pub input: Vec<(String, Vec<Binding>)>,
+}
+
+impl ConfigFile {
+    /// Reads `path`, or `vulkt.toml` in the working directory if it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None if Path::new(DEFAULT_CONFIG_FILE).is_file() => PathBuf::from(DEFAULT_CONFIG_FILE),
+            None => return Ok(Self::default()),
+        };
+        info!("loading config from {}", path.display());
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("can not read {}", path.display()))?;
+        Ok(Self {
+            path: Some(path.clone()),
+            ..Self::parse(&text, &path.display().to_string())?
+        })
+    }
+
+    /// Sets `window.fullscreen` in the config file at `path`, keeping the rest of it as written,
+    /// or creates the file.
+    ///
+    /// Writes a temporary file and renames it over the config file, so an interrupted write does
+    /// not lose the file.
+    pub fn save_fullscreen(path: &Path, mode: FullscreenMode) -> Result<()> {
+        let source = path.display().to_string();
+        let (text, created) = match fs::read_to_string(path) {
+            Ok(text) => (text, false),
+            Err(e) if e.kind() == ErrorKind::NotFound => (String::new(), true),
+            Err(e) => Err(e).with_context(|| format!("can not read {source}"))?,
+        };
+        let mut document = parse_document(&text, &source, config_error)?;
+        let window = document
+            .entry("window")
+            .or_insert(table())
+            .as_table_like_mut()
+            .ok_or_else(|| AppError::ConfigFile {
+                file: source.clone(),
+                message: "window: expected a table".to_string(),
+            })?;
+        window.insert("fullscreen", value(possible_value_name(&mode)));
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, document.to_string())
+            .with_context(|| format!("can not write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("can not rename {} to {source}", tmp_path.display()))?;
+        if created {
+            info!("created config file {source} to save fullscreen mode {mode:?}");
+        } else {
+            info!("saved fullscreen mode {mode:?} to {source}");
+        }
+        Ok(())
+    }
+
+    fn parse(text: &str, source: &str) -> Result<Self> {
+        let document = parse_document(text, source, config_error)?;
+        let root = Section::root(source, &document, config_error);
+        root.check_keys(&["window", "input"])?;
+
+        let mut config = Self::default();
+        if let Some(window) = root.section("window")? {
+            window.check_keys(&[
+                "width",
+                "height",
+                "title",
+                "resizable",
+                "maximized",
+                "decorations",
+                "position",
+                "fullscreen",
+                "video_mode",
+            ])?;
+            config.window = WindowOverrides {
+                width: window.size("width")?,
+                height: window.size("height")?,
+                title: window.str("title")?.map(str::to_string),
+                resizable: window.bool("resizable")?,
+                maximized: window.bool("maximized")?,
+                decorations: window.bool("decorations")?,
+                position: window.position("position")?,
+                fullscreen: window.value_enum("fullscreen")?,
+                video_mode: window.parsed("video_mode")?,
+            };
+        }
+        if let Some(input) = root.section("input")? {
+            input.check_keys(ACTIONS)?;
+            for (action, _) in input.table.iter() {
+                config
+                    .input
+                    .push((action.to_string(), input.bindings(action)?));
+            }
+        }
+        Ok(config)
+    }
+}
+
+fn config_error(file: String, message: String) -> AppError {
+    AppError::ConfigFile { file, message }
+}
+
+/// Name of `value` on the command line and in the config file.
+fn possible_value_name(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|possible_value| possible_value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+impl Section<'_> {
+    /// One of the values of `T` accepted on the command line, as a string.
+    fn value_enum<T: ValueEnum>(&self, key: &str) -> Result<Option<T>> {
+        let Some(name) = self.str(key)? else {
+            return Ok(None);
+        };
+        T::from_str(name, false).map(Some).map_err(|_| {
+            let names: Vec<_> = T::value_variants()
+                .iter()
+                .map(possible_value_name)
+                .collect();
+            self.error(
+                key,
+                format!("unknown value `{name}`, expected one of {names:?}"),
+            )
+        })
+    }
+
+    /// A string parsed with `FromStr`.
+    fn parsed<T: FromStr<Err = String>>(&self, key: &str) -> Result<Option<T>> {
+        self.str(key)?
+            .map(|text| text.parse().map_err(|e| self.error(key, e)))
+            .transpose()
+    }
+
+    /// Key and mouse button bindings, written as one string or an array of strings.
+    fn bindings(&self, key: &str) -> Result<Vec<Binding>> {
+        let Some(item) = self.get(key) else {
+            return Ok(Vec::new());
+        };
+        let error = || self.error(key, "expected a binding or an array of bindings");
+        let names: Vec<_> = if let Some(name) = item.as_str() {
+            vec![name]
+        } else {
+            item.as_array()
+                .ok_or_else(error)?
+                .iter()
+                .map(|value| value.as_str().ok_or_else(error))
+                .collect::<Result<_>>()?
+        };
+        names
+            .into_iter()
+            .map(|name| name.parse().map_err(|e| self.error(key, e)))
+            .collect()
+    }
+
+    /// A positive number of pixels.
+    fn size(&self, key: &str) -> Result<Option<u32>> {
+        self.get(key)
+            .map(|item| {
+                item.as_integer()
+                    .and_then(|value| u32::try_from(value).ok())
+                    .filter(|&value| value > 0)
+                    .ok_or_else(|| self.error(key, "expected a positive integer"))
+            })
+            .transpose()
+    }
+
+    /// Screen coordinates, written as `[x, y]`.
+    fn position(&self, key: &str) -> Result<Option<[i32; 2]>> {
+        self.get(key)
+            .map(|item| {
+                let coordinates = item
+                    .as_array()
+                    .map(|array| {
+                        array
+                            .iter()
+                            .map(|value| value.as_integer().and_then(|v| i32::try_from(v).ok()))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .and_then(|coordinates| <[i32; 2]>::try_from(coordinates?).ok());
+                coordinates.ok_or_else(|| self.error(key, "expected an array of two integers"))
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VideoModeRequest;
+    use std::{env, process};
+    use winit::event::VirtualKeyCode;
+
+    fn parse(text: &str) -> Result<ConfigFile> {
+        ConfigFile::parse(text, "test.toml")
+    }
+
+    fn parse_error(text: &str) -> String {
+        parse(text).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_the_example_file() {
+        let config = parse(include_str!("../vulkt.example.toml")).unwrap();
+        assert_eq!(
+            config.window,
+            WindowOverrides {
+                width: Some(800),
+                height: Some(600),
+                title: Some("Vulkan Tutorial".to_string()),
+                resizable: Some(false),
+                maximized: Some(false),
+                decorations: Some(true),
+                position: None,
+                fullscreen: Some(FullscreenMode::Windowed),
+                video_mode: None,
+            }
+        );
+        let actions: Vec<_> = config
+            .input
+            .iter()
+            .map(|(action, _)| action.as_str())
+            .collect();
+        assert_eq!(actions, ACTIONS);
+    }
+
+    #[test]
+    fn parses_window_settings() {
+        let config = parse(
+            "[window]\nposition = [-10, 20]\nfullscreen = \"exclusive\"\n\
+             video_mode = \"1920x1080@60\"",
+        )
+        .unwrap();
+        assert_eq!(config.window.position, Some([-10, 20]));
+        assert_eq!(config.window.fullscreen, Some(FullscreenMode::Exclusive));
+        assert_eq!(
+            config.window.video_mode,
+            Some(VideoModeRequest {
+                width: 1920,
+                height: 1080,
+                refresh_rate: Some(60),
+            })
+        );
+        assert_eq!(config.window.width, None);
+    }
+
+    #[test]
+    fn parses_bindings() {
+        let config =
+            parse("[input]\nmove_up = [\"Space\", \"E\"]\nmove_down = \"Q\"\nlook = []").unwrap();
+        assert_eq!(
+            config.input,
+            [
+                (
+                    "move_up".to_string(),
+                    vec![
+                        Binding::key(VirtualKeyCode::Space),
+                        Binding::key(VirtualKeyCode::E)
+                    ]
+                ),
+                (
+                    "move_down".to_string(),
+                    vec![Binding::key(VirtualKeyCode::Q)]
+                ),
+                ("look".to_string(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        for (text, path) in [
+            ("[windows]", "windows"),
+            (
+                "[window]\nfullscreen_mode = \"exclusive\"",
+                "window.fullscreen_mode",
+            ),
+            ("[input]\njump = \"Space\"", "input.jump"),
+        ] {
+            let error = parse_error(text);
+            assert!(
+                error.contains(&format!("{path}: unknown key")),
+                "{text}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_mistyped_values() {
+        for (text, message) in [
+            ("window = 1", "window: expected a table"),
+            (
+                "[window]\nwidth = 0",
+                "window.width: expected a positive integer",
+            ),
+            (
+                "[window]\nheight = \"600\"",
+                "window.height: expected a positive integer",
+            ),
+            ("[window]\ntitle = 1", "window.title: expected a string"),
+            (
+                "[window]\nresizable = \"yes\"",
+                "window.resizable: expected a boolean",
+            ),
+            (
+                "[window]\nposition = [1]",
+                "window.position: expected an array of two integers",
+            ),
+            (
+                "[window]\nfullscreen = \"maximal\"",
+                "window.fullscreen: unknown value `maximal`",
+            ),
+            (
+                "[window]\nvideo_mode = \"1080p\"",
+                "window.video_mode: expected WIDTHxHEIGHT",
+            ),
+            (
+                "[input]\nlook = 1",
+                "input.look: expected a binding or an array of bindings",
+            ),
+            (
+                "[input]\nlook = \"MouseSide\"",
+                "input.look: unknown key or mouse button",
+            ),
+            ("[window", "test.toml"),
+        ] {
+            let error = parse_error(text);
+            assert!(error.contains(message), "{text}: {error}");
+        }
+    }
+
+    #[test]
+    fn saves_fullscreen_keeping_the_rest_of_the_file() {
+        let dir = env::temp_dir().join(format!("vulkt-config-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("kept.toml");
+        let text = "# comment\n[window]\ntitle = \"kept\"  # inline\nfullscreen = \"windowed\"\n";
+        fs::write(&path, text).unwrap();
+        ConfigFile::save_fullscreen(&path, FullscreenMode::Borderless).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            text.replace("\"windowed\"", "\"borderless\"")
+        );
+
+        let path = dir.join("added.toml");
+        fs::write(&path, "[input]\nlook = \"MouseLeft\"\n").unwrap();
+        ConfigFile::save_fullscreen(&path, FullscreenMode::Exclusive).unwrap();
+        let config = ConfigFile::parse(&fs::read_to_string(&path).unwrap(), "added.toml").unwrap();
+        assert_eq!(config.window.fullscreen, Some(FullscreenMode::Exclusive));
+        assert_eq!(config.input.len(), 1);
+
+        let path = dir.join("created.toml");
+        ConfigFile::save_fullscreen(&path, FullscreenMode::Borderless).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[window]\nfullscreen = \"borderless\"\n"
+        );
+
+        let path = dir.join("invalid.toml");
+        fs::write(&path, "window = 1\n").unwrap();
+        let error = ConfigFile::save_fullscreen(&path, FullscreenMode::Borderless).unwrap_err();
+        assert!(
+            error.to_string().contains("window: expected a table"),
+            "{error}"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "window = 1\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Most update steps run for one frame. Time beyond that is dropped, so updates that take
+/// longer than the time they simulate slow the simulation down instead of falling ever further
+/// behind.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Splits the time between frames into update steps of a fixed length, so the simulation does
+/// not depend on the frame rate.
+pub struct FixedTimestep {
+    timestep: Duration,
+    /// Time not simulated yet, less than a step after `advance`
+    accumulator: Duration,
+    last_frame: Option<Instant>,
+}
+
+impl FixedTimestep {
+    pub fn new(update_rate: u32) -> Self {
+        Self {
+            timestep: Duration::from_secs(1) / update_rate.max(1),
+            accumulator: Duration::ZERO,
+            last_frame: None,
+        }
+    }
+
+    /// Length of a step in seconds.
+    #[inline]
+    pub fn delta_time(&self) -> f32 {
+        self.timestep.as_secs_f32()
+    }
+
+    /// Adds the time since the last call and returns how many steps are due.
+    pub fn advance(&mut self, now: Instant) -> u32 {
+        if let Some(last_frame) = self.last_frame {
+            self.accumulator += now.saturating_duration_since(last_frame);
+        }
+        self.last_frame = Some(now);
+
+        let max_accumulated = self.timestep * MAX_STEPS_PER_FRAME;
+        if self.accumulator > max_accumulated {
+            debug!(
+                "updates are behind, dropping {:?} of simulation time",
+                self.accumulator - max_accumulated
+            );
+            self.accumulator = max_accumulated;
+        }
+        let steps = (self.accumulator.as_nanos() / self.timestep.as_nanos()) as u32;
+        self.accumulator -= self.timestep * steps;
+        steps
+    }
+
+    /// How far the time since the last step is into the next one, from 0 to 1, for
+    /// interpolating between the states of the last two steps.
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.timestep.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    fn timestep() -> (FixedTimestep, Instant) {
+        let start = Instant::now();
+        let mut timestep = FixedTimestep::new(100);
+        assert_eq!(timestep.advance(start), 0);
+        (timestep, start)
+    }
+
+    #[test]
+    fn first_frame_runs_no_step() {
+        let (timestep, _) = timestep();
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.delta_time(), 0.01);
+    }
+
+    #[test]
+    fn runs_whole_steps_and_keeps_the_remainder() {
+        let (mut timestep, start) = timestep();
+        assert_eq!(timestep.advance(start + STEP * 5 / 2), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+        // The remainder and the next frame add up to another step
+        assert_eq!(timestep.advance(start + STEP * 29 / 10), 0);
+        assert_eq!(timestep.advance(start + STEP * 32 / 10), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn clamps_steps_after_a_stall() {
+        let (mut timestep, start) = timestep();
+        assert_eq!(
+            timestep.advance(start + Duration::from_secs(10)),
+            MAX_STEPS_PER_FRAME
+        );
+        assert_eq!(timestep.alpha(), 0.0);
+        // The dropped time is not caught up on later
+        assert_eq!(
+            timestep.advance(start + Duration::from_secs(10) + STEP / 2),
+            0
+        );
+    }
+
+    #[test]
+    fn alpha_stays_below_one() {
+        let (mut timestep, start) = timestep();
+        let mut now = start;
+        for i in 0..1000u32 {
+            now += Duration::from_micros(u64::from(i * 7919 % 23_000));
+            timestep.advance(now);
+            let alpha = timestep.alpha();
+            assert!((0.0..1.0).contains(&alpha), "alpha {alpha} out of range");
+        }
+    }
+
+    #[test]
+    fn ignores_time_going_backwards() {
+        let (mut timestep, start) = timestep();
+        assert_eq!(timestep.advance(start + STEP), 1);
+        assert_eq!(timestep.advance(start), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+/// Caps the frame rate by waiting on the CPU between frames.
+pub struct FrameLimiter {
+    frame_time: Duration,
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: u32) -> Self {
+        Self {
+            frame_time: Duration::from_secs(1) / max_fps.max(1),
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// Returns when the next frame is due, or `None` if it is due now, scheduling the one after.
+    pub fn poll(&mut self, now: Instant) -> Option<Instant> {
+        if now < self.next_frame {
+            return Some(self.next_frame);
+        }
+        self.next_frame += self.frame_time;
+        if self.next_frame < now {
+            // Too late to catch up, start over instead of drawing a burst of frames
+            self.next_frame = now + self.frame_time;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_TIME: Duration = Duration::from_millis(10);
+
+    fn limiter() -> (FrameLimiter, Instant) {
+        let limiter = FrameLimiter::new(100);
+        let start = limiter.next_frame;
+        (limiter, start)
+    }
+
+    #[test]
+    fn waits_until_the_next_frame_is_due() {
+        let (mut limiter, start) = limiter();
+        assert_eq!(limiter.poll(start), None);
+        assert_eq!(
+            limiter.poll(start + FRAME_TIME / 2),
+            Some(start + FRAME_TIME)
+        );
+        assert_eq!(limiter.poll(start + FRAME_TIME), None);
+        assert_eq!(
+            limiter.poll(start + FRAME_TIME),
+            Some(start + FRAME_TIME * 2)
+        );
+    }
+
+    #[test]
+    fn keeps_the_schedule_when_slightly_late() {
+        let (mut limiter, start) = limiter();
+        assert_eq!(limiter.poll(start), None);
+        // A frame a bit late does not delay the ones after it
+        assert_eq!(limiter.poll(start + FRAME_TIME * 3 / 2), None);
+        assert_eq!(
+            limiter.poll(start + FRAME_TIME * 3 / 2),
+            Some(start + FRAME_TIME * 2)
+        );
+    }
+
+    #[test]
+    fn starts_over_instead_of_catching_up() {
+        let (mut limiter, start) = limiter();
+        assert_eq!(limiter.poll(start), None);
+        let late = start + FRAME_TIME * 10;
+        assert_eq!(limiter.poll(late), None);
+        assert_eq!(limiter.poll(late), Some(late + FRAME_TIME));
+    }
+
+    #[test]
+    fn zero_fps_caps_at_one_frame_per_second() {
+        assert_eq!(FrameLimiter::new(0).frame_time, Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Length of the intervals timings are summarized over.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Frame and update timings over one reporting interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTimings {
+    pub frames_per_second: f32,
+    pub updates_per_second: f32,
+    pub average_frame_time: Duration,
+    pub min_frame_time: Duration,
+    pub max_frame_time: Duration,
+}
+
+/// Measures the time between rendered frames and counts update steps, summarizing them every
+/// second.
+pub struct FrameStats {
+    interval_start: Instant,
+    last_frame: Option<Instant>,
+    frames: u32,
+    updates: u32,
+    min_frame_time: Duration,
+    max_frame_time: Duration,
+    latest: Option<FrameTimings>,
+}
+
+impl FrameStats {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            interval_start: now,
+            last_frame: None,
+            frames: 0,
+            updates: 0,
+            min_frame_time: Duration::MAX,
+            max_frame_time: Duration::ZERO,
+            latest: None,
+        }
+    }
+
+    /// Timings of the last complete interval, `None` during the first one.
+    #[inline]
+    pub fn latest(&self) -> Option<&FrameTimings> {
+        self.latest.as_ref()
+    }
+
+    pub fn record_updates(&mut self, steps: u32) {
+        self.updates += steps;
+    }
+
+    /// Records a frame rendered at `now`, closing the interval if it is over.
+    pub fn record_frame(&mut self, now: Instant) {
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            let frame_time = now.saturating_duration_since(last_frame);
+            self.frames += 1;
+            self.min_frame_time = self.min_frame_time.min(frame_time);
+            self.max_frame_time = self.max_frame_time.max(frame_time);
+        }
+
+        let elapsed = now.saturating_duration_since(self.interval_start);
+        if elapsed < REPORT_INTERVAL {
+            return;
+        }
+        let seconds = elapsed.as_secs_f32();
+        let timings = FrameTimings {
+            frames_per_second: self.frames as f32 / seconds,
+            updates_per_second: self.updates as f32 / seconds,
+            average_frame_time: elapsed / self.frames.max(1),
+            min_frame_time: self.min_frame_time.min(self.max_frame_time),
+            max_frame_time: self.max_frame_time,
+        };
+        debug!(
+            "{:.1} fps, {:.1} updates/s, frame time {:?} average, {:?} min, {:?} max",
+            timings.frames_per_second,
+            timings.updates_per_second,
+            timings.average_frame_time,
+            timings.min_frame_time,
+            timings.max_frame_time
+        );
+        *self = Self {
+            last_frame: self.last_frame,
+            latest: Some(timings),
+            ..Self::new(now)
+        };
+    }
+}
@@ -0,0 +1,419 @@
+use ahash::HashSet;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use winit::event::{
+    DeviceEvent, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+
+pub const MOVE_FORWARD: &str = "move_forward";
+pub const MOVE_BACKWARD: &str = "move_backward";
+pub const MOVE_LEFT: &str = "move_left";
+pub const MOVE_RIGHT: &str = "move_right";
+pub const MOVE_UP: &str = "move_up";
+pub const MOVE_DOWN: &str = "move_down";
+/// Held to turn the camera with the mouse
+pub const LOOK: &str = "look";
+pub const TOGGLE_FULLSCREEN: &str = "toggle_fullscreen";
+
+/// Actions the application queries, the ones a config file can rebind.
+pub const ACTIONS: &[&str] = &[
+    MOVE_FORWARD,
+    MOVE_BACKWARD,
+    MOVE_LEFT,
+    MOVE_RIGHT,
+    MOVE_UP,
+    MOVE_DOWN,
+    LOOK,
+    TOGGLE_FULLSCREEN,
+];
+
+/// Wheel movement reported in pixels, by touchpads, is converted to lines at this ratio.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// A key or mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+/// A key or mouse button with the modifier keys that have to be held with it, written like
+/// `W`, `Alt+Return` or `Ctrl+MouseLeft`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub modifiers: ModifiersState,
+    pub trigger: Trigger,
+}
+
+impl Binding {
+    pub fn key(key: VirtualKeyCode) -> Self {
+        Self {
+            modifiers: ModifiersState::empty(),
+            trigger: Trigger::Key(key),
+        }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self {
+            modifiers: ModifiersState::empty(),
+            trigger: Trigger::Mouse(button),
+        }
+    }
+
+    pub fn with_modifiers(self, modifiers: ModifiersState) -> Self {
+        Self { modifiers, ..self }
+    }
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        &[$((stringify!($key), VirtualKeyCode::$key)),*]
+    };
+}
+
+/// Names of the keys bindings can use, the names of `VirtualKeyCode` variants.
+#[rustfmt::skip]
+const KEYS: &[(&str, VirtualKeyCode)] = key_names!(
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0, A, B, C, D, E, F, G, H, I, J, K,
+    L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10,
+    F11, F12, Snapshot, Scroll, Pause, Insert, Home, Delete, End, PageDown, PageUp, Left, Up,
+    Right, Down, Back, Return, Space, Numlock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4,
+    Numpad5, Numpad6, Numpad7, Numpad8, Numpad9, NumpadAdd, NumpadDivide, NumpadDecimal,
+    NumpadEnter, NumpadMultiply, NumpadSubtract, Apostrophe, Backslash, Capital, Comma, Equals,
+    Grave, LAlt, LBracket, LControl, LShift, LWin, Minus, Period, RAlt, RBracket, RControl,
+    RShift, RWin, Semicolon, Slash, Tab,
+);
+
+const MODIFIERS: &[(&str, ModifiersState)] = &[
+    ("Shift", ModifiersState::SHIFT),
+    ("Ctrl", ModifiersState::CTRL),
+    ("Alt", ModifiersState::ALT),
+    ("Super", ModifiersState::LOGO),
+];
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] = &[
+    ("MouseLeft", MouseButton::Left),
+    ("MouseRight", MouseButton::Right),
+    ("MouseMiddle", MouseButton::Middle),
+];
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<_> = s.split('+').map(str::trim).collect();
+        let trigger = parts.pop().unwrap_or_default();
+        let mut modifiers = ModifiersState::empty();
+        for part in parts {
+            modifiers |= find_name(MODIFIERS, part)
+                .ok_or_else(|| format!("unknown modifier `{part}` in `{s}`"))?;
+        }
+        let trigger = if let Some(key) = find_name(KEYS, trigger) {
+            Trigger::Key(key)
+        } else if let Some(button) = find_name(MOUSE_BUTTONS, trigger) {
+            Trigger::Mouse(button)
+        } else if let Some(Ok(button)) = trigger.strip_prefix("Mouse").map(str::parse) {
+            Trigger::Mouse(MouseButton::Other(button))
+        } else if trigger.eq_ignore_ascii_case("Enter") {
+            Trigger::Key(VirtualKeyCode::Return)
+        } else {
+            return Err(format!(
+                "unknown key or mouse button `{trigger}` in `{s}`, expected a winit \
+                 VirtualKeyCode name, MouseLeft, MouseRight, MouseMiddle or Mouse<N>"
+            ));
+        };
+        Ok(Self { modifiers, trigger })
+    }
+}
+
+/// The value named `name` in `names`, ignoring case.
+fn find_name<T: Copy>(names: &[(&str, T)], name: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, value)| value)
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (name, modifier) in MODIFIERS {
+            if self.modifiers.contains(*modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match self.trigger {
+            Trigger::Key(key) => write!(f, "{key:?}"),
+            Trigger::Mouse(MouseButton::Other(button)) => write!(f, "Mouse{button}"),
+            Trigger::Mouse(button) => write!(f, "Mouse{button:?}"),
+        }
+    }
+}
+
+/// Bindings of named actions, any of which activates the action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut map = Self {
+            actions: BTreeMap::new(),
+        };
+        map.bind(MOVE_FORWARD, vec![Binding::key(VirtualKeyCode::W)]);
+        map.bind(MOVE_BACKWARD, vec![Binding::key(VirtualKeyCode::S)]);
+        map.bind(MOVE_LEFT, vec![Binding::key(VirtualKeyCode::A)]);
+        map.bind(MOVE_RIGHT, vec![Binding::key(VirtualKeyCode::D)]);
+        map.bind(MOVE_UP, vec![Binding::key(VirtualKeyCode::Space)]);
+        map.bind(MOVE_DOWN, vec![Binding::key(VirtualKeyCode::LShift)]);
+        map.bind(LOOK, vec![Binding::mouse(MouseButton::Right)]);
+        map.bind(
+            TOGGLE_FULLSCREEN,
+            vec![Binding::key(VirtualKeyCode::Return).with_modifiers(ModifiersState::ALT)],
+        );
+        map
+    }
+}
+
+impl InputMap {
+    /// Replaces the bindings of `action`; no bindings leave it unbound.
+    pub fn bind(&mut self, action: impl Into<String>, bindings: Vec<Binding>) {
+        self.actions.insert(action.into(), bindings);
+    }
+
+    /// Bindings of `action`, empty for unknown actions.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = (&str, &[Binding])> {
+        self.actions
+            .iter()
+            .map(|(action, bindings)| (action.as_str(), bindings.as_slice()))
+    }
+}
+
+/// Keyboard and mouse state fed from window events, queried through the actions of an
+/// `InputMap`.
+///
+/// What happened since the last `end_frame` call, pressed and released triggers, mouse and wheel
+/// movement, is kept until the next one.
+#[derive(Debug)]
+pub struct Input {
+    pub map: InputMap,
+    held: HashSet<Trigger>,
+    pressed: HashSet<Trigger>,
+    released: HashSet<Trigger>,
+    modifiers: ModifiersState,
+    focused: bool,
+    cursor_position: Option<[f64; 2]>,
+    mouse_delta: [f64; 2],
+    wheel_delta: [f64; 2],
+}
+
+impl Input {
+    pub fn new(map: InputMap) -> Self {
+        Self {
+            map,
+            held: HashSet::default(),
+            pressed: HashSet::default(),
+            released: HashSet::default(),
+            modifiers: ModifiersState::empty(),
+            focused: true,
+            cursor_position: None,
+            mouse_delta: [0.0; 2],
+            wheel_delta: [0.0; 2],
+        }
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => self.set_state(Trigger::Key(key), state),
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.set_state(Trigger::Mouse(button), state)
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some([position.x, position.y])
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let [x, y] = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => [x as f64, y as f64],
+                    MouseScrollDelta::PixelDelta(position) => {
+                        [position.x / PIXELS_PER_LINE, position.y / PIXELS_PER_LINE]
+                    }
+                };
+                self.wheel_delta[0] += x;
+                self.wheel_delta[1] += y;
+            }
+            WindowEvent::Focused(focused) => {
+                self.focused = focused;
+                if !focused {
+                    // Release events of keys held while switching windows never arrive
+                    self.released.extend(self.held.drain());
+                    self.modifiers = ModifiersState::empty();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Accumulates raw mouse motion, not limited by the window or screen edges.
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = *event {
+            if self.focused {
+                self.mouse_delta[0] += x;
+                self.mouse_delta[1] += y;
+            }
+        }
+    }
+
+    /// Forgets what happened since the last call, once the application has reacted to it.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.mouse_delta = [0.0; 2];
+        self.wheel_delta = [0.0; 2];
+    }
+
+    /// Whether a binding of `action` is held down.
+    pub fn is_active(&self, action: &str) -> bool {
+        self.any_binding(action, &self.held)
+    }
+
+    /// Whether a binding of `action` was pressed since the last frame.
+    pub fn was_activated(&self, action: &str) -> bool {
+        self.any_binding(action, &self.pressed)
+    }
+
+    /// Whether a binding of `action` was released since the last frame.
+    pub fn was_deactivated(&self, action: &str) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| self.released.contains(&binding.trigger))
+    }
+
+    /// 1 if only the `positive` action is active, -1 if only the `negative` one is, 0 otherwise.
+    pub fn axis(&self, negative: &str, positive: &str) -> f32 {
+        self.is_active(positive) as i8 as f32 - self.is_active(negative) as i8 as f32
+    }
+
+    /// Mouse movement since the last frame, in unaccelerated device units.
+    pub fn mouse_delta(&self) -> [f64; 2] {
+        self.mouse_delta
+    }
+
+    /// Wheel movement since the last frame in lines, positive away from the user and right.
+    pub fn wheel_delta(&self) -> [f64; 2] {
+        self.wheel_delta
+    }
+
+    /// Cursor position in physical pixels from the top left of the window, `None` outside it.
+    pub fn cursor_position(&self) -> Option<[f64; 2]> {
+        self.cursor_position
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    fn set_state(&mut self, trigger: Trigger, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                // Key repeat sends presses of held keys
+                if self.held.insert(trigger) {
+                    self.pressed.insert(trigger);
+                }
+            }
+            ElementState::Released => {
+                if self.held.remove(&trigger) {
+                    self.released.insert(trigger);
+                }
+            }
+        }
+    }
+
+    fn any_binding(&self, action: &str, triggers: &HashSet<Trigger>) -> bool {
+        self.map.bindings(action).iter().any(|binding| {
+            triggers.contains(&binding.trigger) && self.modifiers.contains(binding.modifiers)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Binding, String> {
+        s.parse()
+    }
+
+    #[test]
+    fn parses_keys_and_mouse_buttons() {
+        assert_eq!(parse("W"), Ok(Binding::key(VirtualKeyCode::W)));
+        assert_eq!(parse("space"), Ok(Binding::key(VirtualKeyCode::Space)));
+        assert_eq!(parse("Enter"), Ok(Binding::key(VirtualKeyCode::Return)));
+        assert_eq!(parse("MouseLeft"), Ok(Binding::mouse(MouseButton::Left)));
+        assert_eq!(parse("Mouse4"), Ok(Binding::mouse(MouseButton::Other(4))));
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert_eq!(
+            parse("Alt+Return"),
+            Ok(Binding::key(VirtualKeyCode::Return).with_modifiers(ModifiersState::ALT))
+        );
+        assert_eq!(
+            parse("ctrl + shift + MouseRight"),
+            Ok(Binding::mouse(MouseButton::Right)
+                .with_modifiers(ModifiersState::CTRL | ModifiersState::SHIFT))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!(parse("Hyper+W")
+            .unwrap_err()
+            .contains("unknown modifier `Hyper`"));
+        assert!(parse("Foo")
+            .unwrap_err()
+            .contains("unknown key or mouse button `Foo`"));
+        assert!(parse("Mouse").is_err());
+        assert!(parse("MouseX").is_err());
+        assert!(parse("").is_err());
+        assert!(parse("Alt+").is_err());
+        assert!(parse("W+Alt").is_err());
+    }
+
+    #[test]
+    fn display_parses_back() {
+        let bindings = KEYS
+            .iter()
+            .map(|&(_, key)| Binding::key(key))
+            .chain(
+                MOUSE_BUTTONS
+                    .iter()
+                    .map(|&(_, button)| Binding::mouse(button)),
+            )
+            .chain([
+                Binding::mouse(MouseButton::Other(7)),
+                Binding::key(VirtualKeyCode::F5)
+                    .with_modifiers(ModifiersState::ALT | ModifiersState::LOGO),
+            ]);
+        for binding in bindings {
+            assert_eq!(parse(&binding.to_string()), Ok(binding));
+        }
+    }
+}
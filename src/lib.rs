@@ -1,4 +1,15 @@
 pub mod app_error;
 pub mod application;
+pub mod camera;
+pub mod config;
+pub mod config_file;
+mod fixed_timestep;
+mod frame_limiter;
+pub mod frame_stats;
+pub mod input;
+pub mod particles;
+mod toml_section;
 mod vulkan;
 mod window;
+
+pub use crate::vulkan::ObjectPushConstants;
@@ -1,6 +1,23 @@
-use clap::Parser;
+use anyhow::Result;
+use clap::builder::BoolishValueParser;
+use clap::{Parser, ValueEnum};
+use serde_json::json;
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use tracing::info;
+use vulkt::app_error::AppError;
 use vulkt::application::Application;
+use vulkt::config::{
+    AppConfig, CameraControl, FramePacing, FullscreenMode, PresentModePreference,
+    SurfaceFormatPreference, VideoModeRequest, WindowConfig, WindowOverrides,
+};
+use vulkt::config_file::ConfigFile;
+use vulkt::input::InputMap;
+
+/// Additional instance layers, separated like `PATH`, added to the ones given with `--layer`.
+const LAYERS_ENV: &str = "VULKT_INSTANCE_LAYERS";
+const LAYER_DELIMITER: char = if cfg!(windows) { ';' } else { ':' };
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -8,16 +25,248 @@ struct Args {
     /// Enable validation layer
     #[arg(long)]
     validate: bool,
+    /// Enable an additional instance layer, can be repeated; layers listed in
+    /// VULKT_INSTANCE_LAYERS are enabled as well
+    #[arg(long = "layer", value_name = "NAME")]
+    layers: Vec<String>,
+    /// Vertex shader to load at runtime: SPIR-V (.spv), HLSL (.hlsl) or GLSL. Only load SPIR-V
+    /// files you trust, they are not validated
+    #[arg(long, value_name = "PATH")]
+    vertex_shader: Option<PathBuf>,
+    /// Fragment shader to load at runtime: SPIR-V (.spv), HLSL (.hlsl) or GLSL. Only load SPIR-V
+    /// files you trust, they are not validated
+    #[arg(long, value_name = "PATH")]
+    fragment_shader: Option<PathBuf>,
+    /// Entry point of the shaders loaded at runtime [default: main]
+    #[arg(long, value_name = "NAME")]
+    shader_entry_point: Option<String>,
+    /// Directory searched by `#include` in shaders compiled at runtime
+    #[arg(long, value_name = "DIR")]
+    shader_root: Option<PathBuf>,
+    /// Pipeline description (TOML) to build the graphics pipeline from
+    #[arg(long, value_name = "PATH")]
+    pipeline: Option<PathBuf>,
+    /// Variant of the pipeline description, selecting a set of specialization constants
+    #[arg(long, value_name = "NAME")]
+    pipeline_variant: Option<String>,
+    /// Use dynamic rendering (Vulkan 1.3 or VK_KHR_dynamic_rendering) instead of a render pass,
+    /// falling back to the render pass if the device does not support it
+    #[arg(long)]
+    dynamic_rendering: bool,
+    /// Show a particle fountain, simulated with compute shaders
+    #[arg(long)]
+    particles: bool,
+    /// Recompile shaders and rebuild the pipeline when shader, include or pipeline files change
+    #[arg(long)]
+    hot_reload: bool,
+    /// Present mode, falling back to another one if the device does not support it
+    #[arg(long, value_enum, default_value_t = PresentModePreference::Auto)]
+    present_mode: PresentModePreference,
+    /// Synchronize with the display, same as --present-mode fifo
+    #[arg(long, conflicts_with = "present_mode")]
+    vsync: bool,
+    /// Cap the frame rate when the present mode is not synchronized with the display
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..))]
+    max_fps: Option<u32>,
+    /// Simulation steps per second; frames in between interpolate the last two steps
+    #[arg(
+        long,
+        value_name = "HZ",
+        default_value_t = 60,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    update_rate: u32,
+    /// Swapchain format and color space, falling back to 8-bit sRGB if the display does not
+    /// support it
+    #[arg(long, value_enum, default_value_t = SurfaceFormatPreference::Auto)]
+    surface_format: SurfaceFormatPreference,
+    /// Number of swapchain images, clamped to what the surface supports
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(2..))]
+    swapchain_images: Option<u32>,
+    /// Pick the number of swapchain images for low latency or for throughput
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = FramePacing::Throughput,
+        conflicts_with = "swapchain_images"
+    )]
+    frame_pacing: FramePacing,
+    /// Config file with defaults for the settings below [default: vulkt.toml if it exists]
+    #[arg(long, value_name = "PATH", env = "VULKT_CONFIG")]
+    config: Option<PathBuf>,
+    /// Inner width of the window in logical pixels
+    #[arg(long, env = "VULKT_WIDTH", value_parser = clap::value_parser!(u32).range(1..))]
+    width: Option<u32>,
+    /// Inner height of the window in logical pixels
+    #[arg(long, env = "VULKT_HEIGHT", value_parser = clap::value_parser!(u32).range(1..))]
+    height: Option<u32>,
+    /// Window title
+    #[arg(long, env = "VULKT_TITLE")]
+    title: Option<String>,
+    /// Let the window be resized
+    #[arg(
+        long,
+        env = "VULKT_RESIZABLE",
+        num_args = 0..=1,
+        default_missing_value = "true",
+        value_parser = BoolishValueParser::new()
+    )]
+    resizable: Option<bool>,
+    /// Open the window maximized
+    #[arg(
+        long,
+        env = "VULKT_MAXIMIZED",
+        num_args = 0..=1,
+        default_missing_value = "true",
+        value_parser = BoolishValueParser::new()
+    )]
+    maximized: Option<bool>,
+    /// Draw the window title bar and borders
+    #[arg(
+        long,
+        env = "VULKT_DECORATIONS",
+        num_args = 0..=1,
+        default_missing_value = "true",
+        value_parser = BoolishValueParser::new()
+    )]
+    decorations: Option<bool>,
+    /// Outer position of the window in physical pixels
+    #[arg(long, value_name = "X,Y", env = "VULKT_POSITION", value_parser = parse_position)]
+    position: Option<[i32; 2]>,
+    /// Start windowed or fullscreen; Alt+Enter switches between the modes
+    #[arg(long, value_enum, env = "VULKT_FULLSCREEN")]
+    fullscreen: Option<FullscreenMode>,
+    /// Resolution and refresh rate for exclusive fullscreen, as WIDTHxHEIGHT[@HZ]
+    #[arg(long, value_name = "MODE", env = "VULKT_VIDEO_MODE")]
+    video_mode: Option<VideoModeRequest>,
+    /// View the scene through a camera moved with the keyboard and mouse, instead of drawing in
+    /// clip space
+    #[arg(long, value_enum)]
+    camera: Option<CameraControl>,
+    /// How to print a fatal error
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
 }
 
-fn main() -> ! {
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+fn main() -> ExitCode {
     tracing_subscriber::fmt::init();
 
     let args = Args::parse();
+    let error_format = args.error_format;
+
+    match app_config(args)
+        .and_then(|config| Application::new(&config))
+        .and_then(Application::run)
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let app_error = e.downcast_ref::<AppError>();
+            let exit_code = app_error.map_or(1, AppError::exit_code);
+            let hint = app_error.and_then(AppError::hint);
+            match error_format {
+                ErrorFormat::Human => report_error_human(&e, hint.as_deref()),
+                ErrorFormat::Json => report_error_json(&e, hint.as_deref(), exit_code),
+            }
+            ExitCode::from(exit_code)
+        }
+    }
+}
 
+/// Settings from the command line and environment, falling back to the config file.
+fn app_config(mut args: Args) -> Result<AppConfig> {
     info!("validation status: {}", args.validate);
+    if let Some(env_layers) = env::var_os(LAYERS_ENV) {
+        // Duplicates are removed when the instance is created
+        args.layers.extend(
+            env_layers
+                .to_string_lossy()
+                .split(LAYER_DELIMITER)
+                .filter(|layer| !layer.is_empty())
+                .map(str::to_string),
+        );
+    }
+    info!("additional layers: {:?}", args.layers);
+
+    let config_file = ConfigFile::load(args.config.as_deref())?;
+    let mut input = InputMap::default();
+    for (action, bindings) in config_file.input {
+        input.bind(action, bindings);
+    }
+    let mut window = WindowConfig::default();
+    window.apply(&config_file.window);
+    window.apply(&WindowOverrides {
+        width: args.width,
+        height: args.height,
+        title: args.title,
+        resizable: args.resizable,
+        maximized: args.maximized,
+        decorations: args.decorations,
+        position: args.position,
+        fullscreen: args.fullscreen,
+        video_mode: args.video_mode,
+    });
+
+    let config = AppConfig {
+        enable_validation: args.validate,
+        layers: args.layers,
+        vertex_shader: args.vertex_shader,
+        fragment_shader: args.fragment_shader,
+        shader_entry_point: args.shader_entry_point,
+        shader_root: args.shader_root,
+        pipeline: args.pipeline,
+        pipeline_variant: args.pipeline_variant,
+        dynamic_rendering: args.dynamic_rendering,
+        particles: args.particles,
+        hot_reload: args.hot_reload,
+        present_mode: if args.vsync {
+            PresentModePreference::Fifo
+        } else {
+            args.present_mode
+        },
+        max_fps: args.max_fps,
+        update_rate: args.update_rate,
+        surface_format: args.surface_format,
+        swapchain_images: args.swapchain_images,
+        frame_pacing: args.frame_pacing,
+        window,
+        config_file: config_file.path,
+        input,
+        camera: args.camera,
+    };
+    info!("effective config: {config:?}");
+    Ok(config)
+}
 
-    let app = Application::new(args.validate).expect("Can not create app");
+fn parse_position(value: &str) -> Result<[i32; 2], String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y, got `{value}`"))?;
+    let coordinate = |c: &str| c.trim().parse::<i32>().map_err(|e| format!("`{c}`: {e}"));
+    Ok([coordinate(x)?, coordinate(y)?])
+}
+
+fn report_error_human(e: &anyhow::Error, hint: Option<&str>) {
+    eprintln!("error: {e}");
+    for cause in e.chain().skip(1) {
+        eprintln!("caused by: {cause}");
+    }
+    if let Some(hint) = hint {
+        eprintln!("hint: {hint}");
+    }
+}
 
-    app.run()
+fn report_error_json(e: &anyhow::Error, hint: Option<&str>, exit_code: u8) {
+    let report = json!({
+        "error": e.to_string(),
+        "causes": e.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+        "hint": hint,
+        "exit_code": exit_code,
+    });
+    eprintln!("{report}");
 }
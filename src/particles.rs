@@ -0,0 +1,79 @@
+use glam::{Vec3, Vec4};
+
+/// Handle of an emitter added to the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EmitterId(pub(crate) u64);
+
+/// Where an emitter spawns particles, how they move and how they look.
+///
+/// Positions are in the space mapped to clip space by the view-projection matrix of the
+/// particles, the identity unless a camera is set, so +Y points down the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterConfig {
+    /// Particles alive at once at most; when exceeded, the oldest ones are respawned first
+    pub capacity: u32,
+    /// Particles spawned per second
+    pub spawn_rate: f32,
+    /// Seconds a particle lives
+    pub lifetime: f32,
+    pub origin: Vec3,
+    /// Radius of the sphere around `origin` particles spawn in
+    pub spawn_radius: f32,
+    /// Initial velocity of the particles
+    pub velocity: Vec3,
+    /// Radius of the sphere a random velocity added to `velocity` is taken from
+    pub velocity_spread: f32,
+    /// Constant acceleration
+    pub gravity: Vec3,
+    /// Fraction of the velocity lost per second
+    pub drag: f32,
+    /// Point the particles are pulled towards
+    pub attractor: Vec3,
+    /// Acceleration towards `attractor`, negative to push particles away
+    pub attractor_strength: f32,
+    /// Color of a spawned particle, blended additively
+    pub start_color: Vec4,
+    /// Color of a particle at the end of its lifetime
+    pub end_color: Vec4,
+    /// Edge length of the quad of a spawned particle, in clip space units
+    pub start_size: f32,
+    /// Edge length of the quad of a particle at the end of its lifetime
+    pub end_size: f32,
+}
+
+impl EmitterConfig {
+    /// The config mirrored vertically, for a camera with +Y up instead of down the window.
+    pub fn flipped_y(self) -> Self {
+        let flip = |v: Vec3| Vec3::new(v.x, -v.y, v.z);
+        Self {
+            origin: flip(self.origin),
+            velocity: flip(self.velocity),
+            gravity: flip(self.gravity),
+            attractor: flip(self.attractor),
+            ..self
+        }
+    }
+}
+
+impl Default for EmitterConfig {
+    /// A fountain rising from the bottom of the window.
+    fn default() -> Self {
+        Self {
+            capacity: 16384,
+            spawn_rate: 4000.0,
+            lifetime: 3.0,
+            origin: Vec3::new(0.0, 0.9, 0.0),
+            spawn_radius: 0.02,
+            velocity: Vec3::new(0.0, -1.6, 0.0),
+            velocity_spread: 0.35,
+            gravity: Vec3::new(0.0, 1.0, 0.0),
+            drag: 0.1,
+            attractor: Vec3::ZERO,
+            attractor_strength: 0.0,
+            start_color: Vec4::new(1.0, 0.6, 0.2, 0.8),
+            end_color: Vec4::new(0.2, 0.1, 1.0, 0.0),
+            start_size: 0.02,
+            end_size: 0.005,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::app_error::AppError;
+use anyhow::Result;
+use std::fmt::Display;
+use toml_edit::{Document, Item, TableLike};
+
+/// Builds the error reported for a problem in a file, from the file name and the message.
+pub type ErrorConstructor = fn(file: String, message: String) -> AppError;
+
+/// Parses the TOML file `source`, reporting syntax errors with `make_error`.
+pub fn parse_document(text: &str, source: &str, make_error: ErrorConstructor) -> Result<Document> {
+    Ok(text
+        .parse::<Document>()
+        .map_err(|e| make_error(source.to_string(), e.to_string()))?)
+}
+
+/// A table of a TOML file, knowing its path for error messages.
+pub struct Section<'a> {
+    /// Name of the file, for error messages
+    source: &'a str,
+    /// Dotted path of the table, empty for the root table
+    path: String,
+    pub table: &'a dyn TableLike,
+    make_error: ErrorConstructor,
+}
+
+impl<'a> Section<'a> {
+    /// The root table of `document`, read from `source`.
+    pub fn root(source: &'a str, document: &'a Document, make_error: ErrorConstructor) -> Self {
+        Self {
+            source,
+            path: String::new(),
+            table: document.as_table(),
+            make_error,
+        }
+    }
+
+    /// `table`, found at `path` in the same file.
+    pub fn child(&self, path: String, table: &'a dyn TableLike) -> Self {
+        Self {
+            source: self.source,
+            path,
+            table,
+            make_error: self.make_error,
+        }
+    }
+
+    pub fn key_path(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{key}", self.path)
+        }
+    }
+
+    pub fn error(&self, key: &str, message: impl Display) -> anyhow::Error {
+        (self.make_error)(
+            self.source.to_string(),
+            format!("{}: {message}", self.key_path(key)),
+        )
+        .into()
+    }
+
+    pub fn check_keys(&self, known: &[&str]) -> Result<()> {
+        for (key, _) in self.table.iter() {
+            if !known.contains(&key) {
+                return Err(self.error(key, format!("unknown key, expected one of {known:?}")));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a Item> {
+        self.table.get(key).filter(|item| !item.is_none())
+    }
+
+    pub fn section(&self, key: &str) -> Result<Option<Section<'a>>> {
+        let Some(item) = self.get(key) else {
+            return Ok(None);
+        };
+        let table = item
+            .as_table_like()
+            .ok_or_else(|| self.error(key, "expected a table"))?;
+        Ok(Some(self.child(self.key_path(key), table)))
+    }
+
+    pub fn str(&self, key: &str) -> Result<Option<&'a str>> {
+        self.get(key)
+            .map(|item| {
+                item.as_str()
+                    .ok_or_else(|| self.error(key, "expected a string"))
+            })
+            .transpose()
+    }
+
+    pub fn bool(&self, key: &str) -> Result<Option<bool>> {
+        self.get(key)
+            .map(|item| {
+                item.as_bool()
+                    .ok_or_else(|| self.error(key, "expected a boolean"))
+            })
+            .transpose()
+    }
+}
@@ -0,0 +1,64 @@
+use crate::vulkan::debug_label::{CommandBufferLabel, RENDER_PASS_COLOR};
+use crate::vulkan::particles::AppParticles;
+use crate::vulkan::push_constants::{push_constants, ObjectPushConstants};
+use crate::vulkan::render_target::RenderTarget;
+use anyhow::Result;
+use smallvec::smallvec;
+use std::sync::Arc;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer,
+};
+use vulkano::device::Queue;
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport};
+use vulkano::pipeline::{DynamicState, GraphicsPipeline, Pipeline};
+use winit::dpi::PhysicalSize;
+
+pub fn record_command_buffer(
+    command_buffer_allocator: &StandardCommandBufferAllocator,
+    queue: &Arc<Queue>,
+    render_target: &RenderTarget,
+    image_index: u32,
+    graphics_pipeline: &Arc<GraphicsPipeline>,
+    object: ObjectPushConstants,
+    particles: Option<&AppParticles>,
+) -> Result<Arc<PrimaryAutoCommandBuffer>> {
+    let mut builder = AutoCommandBufferBuilder::primary(
+        command_buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+
+    {
+        let mut builder =
+            CommandBufferLabel::begin(&mut builder, "render pass", RENDER_PASS_COLOR)?;
+        let extent = render_target.begin(&mut builder, image_index, [0.0, 0.0, 0.0, 1.0].into())?;
+
+        let viewport = Viewport {
+            extent: PhysicalSize::<u32>::from(extent).into(),
+            ..Viewport::default()
+        };
+
+        let scissor = Scissor {
+            extent,
+            ..Scissor::default()
+        };
+
+        builder.bind_pipeline_graphics(graphics_pipeline.clone())?;
+        let dynamic_state = graphics_pipeline.dynamic_state();
+        if dynamic_state.contains(&DynamicState::Viewport) {
+            builder.set_viewport(0, smallvec![viewport.clone()])?;
+        }
+        if dynamic_state.contains(&DynamicState::Scissor) {
+            builder.set_scissor(0, smallvec![scissor])?;
+        }
+        push_constants(&mut builder, graphics_pipeline.layout(), object)?;
+        builder.draw(3, 1, 0, 0)?;
+        if let Some(particles) = particles {
+            particles.draw(&mut builder, &viewport, &scissor)?;
+        }
+        render_target.end(&mut builder)?;
+    }
+
+    Ok(builder.build()?)
+}
@@ -0,0 +1,175 @@
+use crate::vulkan::debug_label::{CommandBufferLabel, COMPUTE_COLOR};
+use crate::vulkan::push_constants::push_constants;
+use crate::vulkan::shader_loader::{entry_point, load_shader_file, CompileSettings};
+use crate::vulkan::specialization::{specialize, Specialization};
+use anyhow::Result;
+use smallvec::{smallvec, SmallVec};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::debug;
+use vulkano::buffer::BufferContents;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::{Device, Queue};
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::compute::ComputePipelineCreateInfo;
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{
+    ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout, PipelineShaderStageCreateInfo,
+};
+use vulkano::shader::{EntryPoint, ShaderStage};
+use vulkano::sync::{GpuFuture, Sharing};
+
+/// Compute pipelines and their dispatches on the compute queue.
+///
+/// Dispatches are chained into the futures of the frames, so vulkano waits on semaphores and
+/// inserts the buffer and image barriers between compute and graphics work.
+pub struct AppCompute {
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    compile_settings: CompileSettings,
+    pipeline_cache: Arc<PipelineCache>,
+    graphics_queue: Arc<Queue>,
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+}
+
+impl AppCompute {
+    pub fn new(
+        device: &Arc<Device>,
+        queue: &Arc<Queue>,
+        graphics_queue: &Arc<Queue>,
+        pipeline_cache: &Arc<PipelineCache>,
+        compile_settings: CompileSettings,
+    ) -> Self {
+        Self {
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            command_buffer_allocator: StandardCommandBufferAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            compile_settings,
+            pipeline_cache: pipeline_cache.clone(),
+            graphics_queue: graphics_queue.clone(),
+            queue: queue.clone(),
+            device: device.clone(),
+        }
+    }
+
+    #[inline]
+    pub fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    /// Whether compute work runs on its own queue family, in parallel with graphics work.
+    #[inline]
+    pub fn is_async(&self) -> bool {
+        self.queue.queue_family_index() != self.graphics_queue.queue_family_index()
+    }
+
+    /// Sharing mode for buffers and images used by both compute and graphics work.
+    pub fn sharing(&self) -> Sharing<SmallVec<[u32; 4]>> {
+        if self.is_async() {
+            Sharing::Concurrent(smallvec![
+                self.queue.queue_family_index(),
+                self.graphics_queue.queue_family_index(),
+            ])
+        } else {
+            Sharing::Exclusive
+        }
+    }
+
+    /// Compiles or loads a compute shader file and builds a pipeline from it.
+    pub fn load_pipeline(
+        &self,
+        path: &Path,
+        entry_point_name: &str,
+        specialization: &Specialization,
+    ) -> Result<Arc<ComputePipeline>> {
+        let shader = load_shader_file(
+            &self.device,
+            path,
+            ShaderStage::Compute,
+            entry_point_name,
+            &self.compile_settings,
+        )?;
+        let entry_point = specialize(
+            &entry_point(
+                &shader.module,
+                &path.display().to_string(),
+                entry_point_name,
+                ShaderStage::Compute,
+            )?,
+            specialization,
+        )?;
+        self.create_pipeline(entry_point)
+    }
+
+    /// Builds a pipeline from a compute shader entry point, with the pipeline layout derived from
+    /// the reflection of the shader.
+    pub fn create_pipeline(&self, entry_point: EntryPoint) -> Result<Arc<ComputePipeline>> {
+        let stage = PipelineShaderStageCreateInfo::new(entry_point);
+        let pipeline_layout = PipelineLayout::new(
+            self.device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(self.device.clone())?,
+        )?;
+        if pipeline_layout.push_constant_ranges().is_empty() {
+            debug!("the compute shader declares no push constants, dispatches will not push any");
+        }
+        Ok(ComputePipeline::new(
+            self.device.clone(),
+            Some(self.pipeline_cache.clone()),
+            ComputePipelineCreateInfo::stage_layout(stage, pipeline_layout),
+        )?)
+    }
+
+    /// Submits a dispatch of `pipeline` after `after`, returning the future of its completion.
+    ///
+    /// `descriptor_writes` fill descriptor set 0 of the pipeline, if the shader declares one.
+    pub fn dispatch<Pc: BufferContents>(
+        &self,
+        after: Box<dyn GpuFuture>,
+        pipeline: &Arc<ComputePipeline>,
+        descriptor_writes: impl IntoIterator<Item = WriteDescriptorSet>,
+        constants: Pc,
+        group_counts: [u32; 3],
+    ) -> Result<Box<dyn GpuFuture>> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        {
+            let mut builder = CommandBufferLabel::begin(&mut builder, "dispatch", COMPUTE_COLOR)?;
+            builder.bind_pipeline_compute(pipeline.clone())?;
+            if let Some(set_layout) = pipeline.layout().set_layouts().first() {
+                let descriptor_set = PersistentDescriptorSet::new(
+                    &self.descriptor_set_allocator,
+                    set_layout.clone(),
+                    descriptor_writes,
+                    [],
+                )?;
+                builder.bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    pipeline.layout().clone(),
+                    0,
+                    descriptor_set,
+                )?;
+            }
+            push_constants(&mut builder, pipeline.layout(), constants)?;
+            builder.dispatch(group_counts)?;
+        }
+        let command_buffer = builder.build()?;
+
+        Ok(after
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_signal_semaphore_and_flush()?
+            .boxed())
+    }
+}
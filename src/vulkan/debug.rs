@@ -1,11 +1,13 @@
 use anyhow::Result;
 use std::sync::Arc;
 use tracing::{debug, error, info, trace, warn};
+use vulkano::device::{Device, DeviceOwned};
 use vulkano::instance::debug::{
     DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger,
     DebugUtilsMessengerCallback, DebugUtilsMessengerCreateInfo,
 };
 use vulkano::instance::Instance;
+use vulkano::VulkanObject;
 
 #[inline]
 pub fn populate_debug_utils_messenger_create_info() -> DebugUtilsMessengerCreateInfo {
@@ -63,3 +65,39 @@ pub fn setup_debug_messenger(instance: &Arc<Instance>) -> Result<DebugUtilsMesse
         populate_debug_utils_messenger_create_info(),
     )?)
 }
+
+/// Assigns a human-readable name to `object`, if `ext_debug_utils` is enabled on the instance.
+pub fn set_object_name<T: VulkanObject + DeviceOwned>(object: &T, name: &str) -> Result<()> {
+    let device = object.device();
+    if device.instance().enabled_extensions().ext_debug_utils {
+        device.set_debug_utils_object_name(object, Some(name))?;
+    }
+    Ok(())
+}
+
+/// Names each of `objects` as `"{name} {index}"`.
+pub fn set_object_names<T: VulkanObject + DeviceOwned>(objects: &[T], name: &str) -> Result<()> {
+    for (i, object) in objects.iter().enumerate() {
+        set_object_name(object, &format!("{name} {i}"))?;
+    }
+    Ok(())
+}
+
+/// Makes a [`Device`] nameable: unlike its children, it is not [`DeviceOwned`] itself.
+pub struct DeviceObject<'a>(pub &'a Arc<Device>);
+
+unsafe impl VulkanObject for DeviceObject<'_> {
+    type Handle = <Device as VulkanObject>::Handle;
+
+    #[inline]
+    fn handle(&self) -> Self::Handle {
+        self.0.handle()
+    }
+}
+
+unsafe impl DeviceOwned for DeviceObject<'_> {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.0
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use tracing::error;
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::{DeviceOwned, Queue};
+use vulkano::instance::debug::DebugUtilsLabel;
+
+pub const RENDER_PASS_COLOR: [f32; 4] = [0.2, 0.6, 1.0, 1.0];
+pub const SUBMIT_COLOR: [f32; 4] = [1.0, 0.6, 0.2, 1.0];
+pub const COMPUTE_COLOR: [f32; 4] = [0.4, 1.0, 0.4, 1.0];
+pub const PARTICLES_COLOR: [f32; 4] = [1.0, 0.4, 0.8, 1.0];
+
+/// Labels are recorded in debug builds only; in release builds this is `false` without looking at
+/// the instance, so the label code is optimized out.
+#[inline]
+fn labels_enabled(object: &impl DeviceOwned) -> bool {
+    cfg!(debug_assertions)
+        && object
+            .device()
+            .instance()
+            .enabled_extensions()
+            .ext_debug_utils
+}
+
+#[inline]
+fn debug_utils_label(name: &str, color: [f32; 4]) -> DebugUtilsLabel {
+    DebugUtilsLabel {
+        label_name: name.to_string(),
+        color,
+        ..DebugUtilsLabel::default()
+    }
+}
+
+/// A labeled region of a command buffer, closed when dropped.
+///
+/// Derefs to the wrapped builder, so commands recorded through it land inside the region.
+/// Does nothing in release builds or if `ext_debug_utils` is not enabled on the instance.
+pub struct CommandBufferLabel<'a, L, A: CommandBufferAllocator> {
+    builder: &'a mut AutoCommandBufferBuilder<L, A>,
+    active: bool,
+}
+
+impl<'a, L, A: CommandBufferAllocator> CommandBufferLabel<'a, L, A> {
+    pub fn begin(
+        builder: &'a mut AutoCommandBufferBuilder<L, A>,
+        name: &str,
+        color: [f32; 4],
+    ) -> Result<Self> {
+        let active = labels_enabled(builder);
+        if active {
+            builder.begin_debug_utils_label(debug_utils_label(name, color))?;
+        }
+        Ok(Self { builder, active })
+    }
+}
+
+impl<L, A: CommandBufferAllocator> Deref for CommandBufferLabel<'_, L, A> {
+    type Target = AutoCommandBufferBuilder<L, A>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.builder
+    }
+}
+
+impl<L, A: CommandBufferAllocator> DerefMut for CommandBufferLabel<'_, L, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.builder
+    }
+}
+
+impl<L, A: CommandBufferAllocator> Drop for CommandBufferLabel<'_, L, A> {
+    fn drop(&mut self) {
+        if self.active {
+            // SAFETY: the region was begun in `begin` on the same builder
+            if let Err(e) = unsafe { self.builder.end_debug_utils_label() } {
+                error!("can not end command buffer label: {e}");
+            }
+        }
+    }
+}
+
+/// A labeled region of queue operations, closed when dropped.
+///
+/// Does nothing in release builds or if `ext_debug_utils` is not enabled on the instance.
+pub struct QueueLabel<'a> {
+    queue: &'a Arc<Queue>,
+    active: bool,
+}
+
+impl<'a> QueueLabel<'a> {
+    pub fn begin(queue: &'a Arc<Queue>, name: &str, color: [f32; 4]) -> Result<Self> {
+        let active = labels_enabled(queue);
+        if active {
+            queue
+                .with(|mut queue| queue.begin_debug_utils_label(debug_utils_label(name, color)))?;
+        }
+        Ok(Self { queue, active })
+    }
+}
+
+impl Drop for QueueLabel<'_> {
+    fn drop(&mut self) {
+        if self.active {
+            // SAFETY: the region was begun in `begin` on the same queue
+            if let Err(e) = self
+                .queue
+                .with(|mut queue| unsafe { queue.end_debug_utils_label() })
+            {
+                error!("can not end queue label: {e}");
+            }
+        }
+    }
+}
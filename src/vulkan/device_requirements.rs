@@ -0,0 +1,125 @@
+use crate::app_error::DeviceRejection;
+use crate::config::AppConfig;
+use tracing::info;
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::device::{DeviceExtensions, Features};
+use vulkano::Version;
+
+/// Vulkan version, features and extensions the application needs from a device, and the ones it
+/// uses when the device has them.
+#[derive(Debug, Clone)]
+pub struct DeviceRequirements {
+    /// Devices supporting a lower version are rejected
+    pub min_api_version: Version,
+    /// Highest version the instance is created for, used where the loader and the device
+    /// support it
+    pub max_api_version: Version,
+    pub features: Features,
+    pub optional_features: Features,
+    pub extensions: DeviceExtensions,
+    pub optional_extensions: DeviceExtensions,
+}
+
+/// Version, features and extensions a device is created with, optional ones included if the
+/// device supports them.
+#[derive(Debug, Clone)]
+pub struct DeviceCapabilities {
+    /// Version the device is used with: the lowest of the device, loader and instance versions
+    pub api_version: Version,
+    /// Version the device driver reports
+    pub device_api_version: Version,
+    pub features: Features,
+    pub extensions: DeviceExtensions,
+}
+
+impl DeviceRequirements {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            // The embedded shaders are SPIR-V 1.6, which needs Vulkan 1.3
+            min_api_version: Version::V1_3,
+            max_api_version: Version::V1_4,
+            features: Features::empty(),
+            optional_features: Features {
+                sampler_anisotropy: true,
+                fill_mode_non_solid: true,
+                wide_lines: true,
+                descriptor_indexing: true,
+                // Core since Vulkan 1.3, but still has to be enabled
+                dynamic_rendering: config.dynamic_rendering,
+                ..Features::empty()
+            },
+            extensions: DeviceExtensions {
+                khr_swapchain: true,
+                ..DeviceExtensions::empty()
+            },
+            optional_extensions: DeviceExtensions::empty(),
+        }
+    }
+
+    /// What to create a logical device for `physical_device` with, or why it can not be used.
+    pub fn negotiate(
+        &self,
+        physical_device: &PhysicalDevice,
+    ) -> Result<DeviceCapabilities, Vec<DeviceRejection>> {
+        let device_api_version = physical_device.properties().api_version;
+        let api_version = physical_device
+            .api_version()
+            .min(physical_device.instance().api_version())
+            .min(self.max_api_version);
+        let supported_features = physical_device.supported_features();
+        let supported_extensions = physical_device.supported_extensions();
+
+        let mut rejections = Vec::new();
+        if api_version < self.min_api_version {
+            rejections.push(DeviceRejection::ApiVersion {
+                required: self.min_api_version,
+                supported: api_version,
+            });
+        }
+        let missing_features = names(self.features - *supported_features);
+        if !missing_features.is_empty() {
+            rejections.push(DeviceRejection::MissingFeatures(missing_features));
+        }
+        let missing_extensions = names(self.extensions - *supported_extensions);
+        if !missing_extensions.is_empty() {
+            rejections.push(DeviceRejection::MissingExtensions(missing_extensions));
+        }
+        if !rejections.is_empty() {
+            return Err(rejections);
+        }
+
+        Ok(DeviceCapabilities {
+            api_version,
+            device_api_version,
+            features: self.features | (self.optional_features & *supported_features),
+            extensions: self.extensions | (self.optional_extensions & *supported_extensions),
+        })
+    }
+
+    /// Logs which optional features and extensions `capabilities` include.
+    pub fn log_optional(&self, capabilities: &DeviceCapabilities) {
+        info!(
+            "using API version {} (device reports {}, instance created for up to {})",
+            capabilities.api_version, capabilities.device_api_version, self.max_api_version
+        );
+        info!(
+            "enabled optional features: {:?}, unavailable: {:?}",
+            names(self.optional_features & capabilities.features),
+            names(self.optional_features - capabilities.features)
+        );
+        if self.optional_extensions != DeviceExtensions::empty() {
+            info!(
+                "enabled optional extensions: {:?}, unavailable: {:?}",
+                names(self.optional_extensions & capabilities.extensions),
+                names(self.optional_extensions - capabilities.extensions)
+            );
+        }
+    }
+}
+
+/// Names of the features or extensions set in `set`.
+fn names(set: impl IntoIterator<Item = (&'static str, bool)>) -> Vec<&'static str> {
+    set.into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+}
@@ -1,52 +1,39 @@
-use crate::app_error::AppError;
-use crate::vulkan::shader::{load_fragment, load_vertex};
-use ahash::HashSet;
+use crate::vulkan::pipeline_description::PipelineDescription;
+use crate::vulkan::render_target::color_attachment_count;
+use crate::vulkan::shader::GraphicsShaders;
+use crate::vulkan::specialization::Specialization;
 use anyhow::Result;
 use smallvec::smallvec;
 use std::sync::Arc;
+use tracing::debug;
 use vulkano::device::Device;
-use vulkano::pipeline::graphics::color_blend::{ColorBlendAttachmentState, ColorBlendState};
-use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
 use vulkano::pipeline::graphics::multisample::MultisampleState;
-use vulkano::pipeline::graphics::rasterization::{CullMode, FrontFace, RasterizationState};
-use vulkano::pipeline::graphics::vertex_input::VertexInputState;
+use vulkano::pipeline::graphics::subpass::PipelineSubpassType;
 use vulkano::pipeline::graphics::viewport::{Scissor, Viewport, ViewportState};
 use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
-use vulkano::pipeline::layout::PipelineLayoutCreateInfo;
-use vulkano::pipeline::{
-    DynamicState, GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo,
-};
-use vulkano::render_pass::{RenderPass, Subpass};
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo};
 use vulkano::swapchain::Swapchain;
 use winit::dpi::PhysicalSize;
 
 pub fn create_graphics_pipeline(
     device: &Arc<Device>,
     swapchain: &Arc<Swapchain>,
-    render_pass: &Arc<RenderPass>,
+    subpass: &PipelineSubpassType,
+    shaders: &GraphicsShaders,
+    description: &PipelineDescription,
+    specialization: &Specialization,
+    pipeline_cache: &Arc<PipelineCache>,
 ) -> Result<(Arc<PipelineLayout>, Arc<GraphicsPipeline>)> {
-    let vert_shader_module = load_vertex(device.clone())?;
-    let frag_shader_module = load_fragment(device.clone())?;
-
-    let vert_shader_entry_point = vert_shader_module
-        .entry_point("main")
-        .ok_or(AppError::EntryPointNotFound)?;
-
-    let frag_shader_entry_point = frag_shader_module
-        .entry_point("main")
-        .ok_or(AppError::EntryPointNotFound)?;
-
+    description.check_features(device.enabled_features())?;
+    let shaders = shaders.specialize(specialization)?;
     let shader_stages = smallvec![
-        PipelineShaderStageCreateInfo::new(vert_shader_entry_point),
-        PipelineShaderStageCreateInfo::new(frag_shader_entry_point),
+        PipelineShaderStageCreateInfo::new(shaders.vertex),
+        PipelineShaderStageCreateInfo::new(shaders.fragment),
     ];
 
-    let dynamic_states = HashSet::from_iter([DynamicState::Viewport, DynamicState::Scissor]);
-
-    let vertex_input_info = VertexInputState::new();
-
-    let input_assembly = InputAssemblyState::default();
-
     let viewport = Viewport {
         extent: PhysicalSize::<u32>::from(swapchain.image_extent()).into(),
         ..Viewport::default()
@@ -63,43 +50,35 @@ pub fn create_graphics_pipeline(
         ..ViewportState::default()
     };
 
-    let rasterizer = RasterizationState {
-        cull_mode: CullMode::Back,
-        front_face: FrontFace::Clockwise,
-        ..RasterizationState::default()
-    };
-
     let multisampling = MultisampleState::default();
 
-    let color_blend_attachment = ColorBlendAttachmentState::default();
-
-    let subpass_id = 0;
-
-    let subpass = Subpass::from(render_pass.clone(), subpass_id)
-        .ok_or(AppError::SubpassNotFound(subpass_id))?;
-
-    let color_blending = ColorBlendState::with_attachment_states(
-        subpass.num_color_attachments(),
-        color_blend_attachment,
-    );
+    let color_blending = ColorBlendState {
+        attachments: description.color_blend_attachments(color_attachment_count(subpass))?,
+        ..ColorBlendState::default()
+    };
 
-    let pipeline_layout_info = PipelineLayoutCreateInfo::default();
+    // Descriptor set layouts and push constant ranges come from the reflection of the shaders
+    let pipeline_layout_info = PipelineDescriptorSetLayoutCreateInfo::from_stages(&shader_stages)
+        .into_pipeline_layout_create_info(device.clone())?;
 
     let pipeline_layout = PipelineLayout::new(device.clone(), pipeline_layout_info)?;
+    if pipeline_layout.push_constant_ranges().is_empty() {
+        debug!("the graphics shaders declare no push constants, draws will not push any");
+    }
 
     let graphics_pipeline = GraphicsPipeline::new(
         device.clone(),
-        None,
+        Some(pipeline_cache.clone()),
         GraphicsPipelineCreateInfo {
             stages: shader_stages,
-            vertex_input_state: Some(vertex_input_info),
-            input_assembly_state: Some(input_assembly),
+            vertex_input_state: Some(description.vertex_input.clone()),
+            input_assembly_state: Some(description.input_assembly),
             viewport_state: Some(viewport_state),
-            rasterization_state: Some(rasterizer),
+            rasterization_state: Some(description.rasterization.clone()),
             multisample_state: Some(multisampling),
             color_blend_state: Some(color_blending),
-            dynamic_state: dynamic_states,
-            subpass: Some(subpass.into()),
+            dynamic_state: description.dynamic_states.clone(),
+            subpass: Some(subpass.clone()),
             ..GraphicsPipelineCreateInfo::layout(pipeline_layout.clone())
         },
     )?;
@@ -1,10 +1,8 @@
 use crate::app_error::AppError;
 use crate::vulkan::debug::populate_debug_utils_messenger_create_info;
 use anyhow::Result;
-use smallvec::SmallVec;
 use std::collections::HashSet;
-use std::iter;
-use std::sync::{Arc, OnceLock};
+use std::sync::Arc;
 use tracing::{debug, enabled, error, info, Level};
 use vulkano::instance::{Instance, InstanceCreateFlags, InstanceCreateInfo, InstanceExtensions};
 use vulkano::swapchain::Surface;
@@ -14,8 +12,11 @@ use winit::event_loop::EventLoop;
 pub fn create_instance(
     event_loop: &EventLoop<()>,
     enable_validation: bool,
+    extra_layers: &[String],
+    max_api_version: Version,
 ) -> Result<Arc<Instance>> {
     let library = VulkanLibrary::new()?;
+    info!("Vulkan library version {}", library.api_version());
 
     let required_extensions = InstanceExtensions {
         ext_debug_utils: enable_validation,
@@ -23,6 +24,13 @@ pub fn create_instance(
     };
     info!("required extensions: {required_extensions:?}");
 
+    // Surfaces only report HDR and extended color spaces with this extension enabled
+    let optional_extensions = InstanceExtensions {
+        ext_swapchain_colorspace: true,
+        ..InstanceExtensions::empty()
+    } & *library.supported_extensions();
+    info!("enabled optional extensions: {optional_extensions:?}");
+
     if enabled!(Level::INFO) {
         let supported_extensions = library.supported_extensions();
         debug!("available extensions: {supported_extensions:?}");
@@ -36,39 +44,41 @@ pub fn create_instance(
     let mut instance_create_info = InstanceCreateInfo {
         engine_name: Some("No Engine".to_string()),
         engine_version: Version::V1_0,
-        enabled_extensions: required_extensions,
+        enabled_extensions: required_extensions | optional_extensions,
         flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
+        max_api_version: Some(max_api_version),
         ..InstanceCreateInfo::application_from_cargo_toml()
     };
 
-    if enable_validation {
-        let required_layers = validation_layers();
+    let required_layers = required_layers(enable_validation, extra_layers);
+    if !required_layers.is_empty() {
         info!("required layers: {required_layers:?}");
 
         {
             let layer_properties: Vec<_> = library.layer_properties()?.collect();
-            let available_layers = layer_properties.iter().map(|layer| layer.name()).collect();
+            let available_layers: HashSet<_> =
+                layer_properties.iter().map(|layer| layer.name()).collect();
             debug!("available layers: {available_layers:?}");
 
-            let mut diff_it = required_layers.difference(&available_layers);
-            if let Some(first_diff) = diff_it.next() {
-                error!(
-                    "unavailable required layers: {:?}",
-                    iter::once(first_diff)
-                        .chain(diff_it)
-                        .collect::<SmallVec<[_; VALIDATION_LAYERS.len()]>>()
-                );
-                Err(AppError::RequiredLayers)?
+            let unavailable_layers: Vec<_> = required_layers
+                .iter()
+                .filter(|layer| !available_layers.contains(layer.as_str()))
+                .cloned()
+                .collect();
+            if !unavailable_layers.is_empty() {
+                error!("unavailable required layers: {unavailable_layers:?}");
+                Err(AppError::RequiredLayers(unavailable_layers))?
             }
         }
 
         info!("all required layers satisfied");
 
-        instance_create_info = InstanceCreateInfo {
-            enabled_layers: required_layers.iter().map(|s| s.to_string()).collect(),
-            debug_utils_messengers: vec![populate_debug_utils_messenger_create_info()],
-            ..instance_create_info
-        };
+        instance_create_info.enabled_layers = required_layers;
+    }
+
+    if enable_validation {
+        instance_create_info.debug_utils_messengers =
+            vec![populate_debug_utils_messenger_create_info()];
     }
 
     Ok(Instance::new(library, instance_create_info)?)
@@ -76,8 +86,22 @@ pub fn create_instance(
 
 const VALIDATION_LAYERS: [&str; 1] = ["VK_LAYER_KHRONOS_validation"];
 
-#[inline]
-fn validation_layers() -> &'static HashSet<&'static str> {
-    static VALIDATION_LAYERS_LOCK: OnceLock<HashSet<&'static str>> = OnceLock::new();
-    VALIDATION_LAYERS_LOCK.get_or_init(|| VALIDATION_LAYERS.iter().copied().collect())
+/// Validation layers (if enabled) followed by `extra_layers`, without duplicates.
+fn required_layers(enable_validation: bool, extra_layers: &[String]) -> Vec<String> {
+    let validation_layers: &[&str] = if enable_validation {
+        &VALIDATION_LAYERS
+    } else {
+        &[]
+    };
+    let mut layers: Vec<String> = Vec::with_capacity(validation_layers.len() + extra_layers.len());
+    for layer in validation_layers
+        .iter()
+        .copied()
+        .chain(extra_layers.iter().map(String::as_str))
+    {
+        if !layers.iter().any(|l| l == layer) {
+            layers.push(layer.to_string());
+        }
+    }
+    layers
 }
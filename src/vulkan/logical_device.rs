@@ -1,28 +1,34 @@
 use crate::app_error::{AppError, QueueFamilyType};
-use crate::vulkan::physical_device::DEVICE_EXTENSIONS;
+use crate::vulkan::device_requirements::DeviceCapabilities;
 use crate::vulkan::QueueFamilyIndices;
 use anyhow::Result;
 use smallvec::SmallVec;
 use std::collections::HashSet;
 use std::sync::Arc;
 use vulkano::device::physical::PhysicalDevice;
-use vulkano::device::{Device, DeviceCreateInfo, Features, Queue, QueueCreateInfo};
+use vulkano::device::{Device, DeviceCreateInfo, Queue, QueueCreateInfo};
 
 pub struct AppLogicalDevice {
     pub device: Arc<Device>,
     pub graphics_queue: Arc<Queue>,
     pub present_queue: Arc<Queue>,
+    pub compute_queue: Option<Arc<Queue>>,
 }
 
 impl AppLogicalDevice {
     pub fn create(
         physical_device: &Arc<PhysicalDevice>,
         queue_family_indices: &QueueFamilyIndices,
+        capabilities: &DeviceCapabilities,
     ) -> Result<AppLogicalDevice> {
-        let queue_create_infos = HashSet::from([
-            queue_family_indices.graphics_family,
-            queue_family_indices.present_family,
-        ])
+        let queue_create_infos = HashSet::<u32>::from_iter(
+            [
+                queue_family_indices.graphics_family,
+                queue_family_indices.present_family,
+            ]
+            .into_iter()
+            .chain(queue_family_indices.compute_family),
+        )
         .into_iter()
         .map(|queue_family_index| QueueCreateInfo {
             queue_family_index,
@@ -30,15 +36,14 @@ impl AppLogicalDevice {
             ..QueueCreateInfo::default()
         })
         .collect();
-        let device_features = Features::default();
         let device_create_info = DeviceCreateInfo {
             queue_create_infos,
-            enabled_features: device_features,
-            enabled_extensions: DEVICE_EXTENSIONS,
+            enabled_features: capabilities.features,
+            enabled_extensions: capabilities.extensions,
             ..DeviceCreateInfo::default()
         };
         let (device, queues) = Device::new(physical_device.clone(), device_create_info)?;
-        let queues: SmallVec<[_; 2]> = queues.collect();
+        let queues: SmallVec<[_; 3]> = queues.collect();
 
         fn find_queue<'a>(
             queues: impl IntoIterator<Item = &'a Arc<Queue>>,
@@ -64,6 +69,10 @@ impl AppLogicalDevice {
                 queue_family_indices.present_family,
                 QueueFamilyType::Present,
             )?,
+            compute_queue: queue_family_indices
+                .compute_family
+                .map(|compute_family| find_queue(&queues, compute_family, QueueFamilyType::Compute))
+                .transpose()?,
         })
     }
 }
@@ -1,103 +1,535 @@
+mod command_buffer;
+mod compute;
 mod debug;
+mod debug_label;
+mod device_requirements;
 mod framebuffers;
 mod graphics_pipeline;
 mod instance;
 mod logical_device;
+mod particles;
 mod physical_device;
+mod pipeline_cache;
+mod pipeline_description;
+mod pipeline_variants;
+mod push_constants;
 mod queue_family_indices;
 mod render_pass;
+mod render_target;
 mod shader;
+mod shader_include;
+mod shader_loader;
+mod shader_watcher;
+mod specialization;
 mod surface;
+mod surface_format;
 mod swapchain;
 
-use crate::vulkan::debug::setup_debug_messenger;
-use crate::vulkan::framebuffers::create_framebuffers;
-use crate::vulkan::graphics_pipeline::create_graphics_pipeline;
+use crate::app_error::AppError;
+use crate::config::AppConfig;
+use crate::particles::{EmitterConfig, EmitterId};
+use crate::vulkan::command_buffer::record_command_buffer;
+use crate::vulkan::compute::AppCompute;
+use crate::vulkan::debug::{
+    set_object_name, set_object_names, setup_debug_messenger, DeviceObject,
+};
+use crate::vulkan::debug_label::{QueueLabel, SUBMIT_COLOR};
+use crate::vulkan::device_requirements::DeviceRequirements;
 use crate::vulkan::instance::create_instance;
 use crate::vulkan::logical_device::AppLogicalDevice;
+use crate::vulkan::particles::AppParticles;
 use crate::vulkan::physical_device::pick_physical_device;
+use crate::vulkan::pipeline_cache::{load_pipeline_cache, save_pipeline_cache};
+use crate::vulkan::pipeline_variants::PipelineVariants;
+pub use crate::vulkan::push_constants::ObjectPushConstants;
 use crate::vulkan::queue_family_indices::QueueFamilyIndices;
-use crate::vulkan::render_pass::create_render_pass;
+use crate::vulkan::render_target::RenderTarget;
+use crate::vulkan::shader::include_dirs;
+use crate::vulkan::shader_include::canonical;
+use crate::vulkan::shader_loader::CompileSettings;
+use crate::vulkan::shader_watcher::ShaderWatcher;
+pub use crate::vulkan::specialization::Specialization;
 use crate::vulkan::surface::create_surface;
-use crate::vulkan::swapchain::create_image_views;
-use anyhow::Result;
+use crate::vulkan::surface_format::OutputTransfer;
+use crate::vulkan::swapchain::{choose_swap_extent, create_image_views};
+use anyhow::{bail, Result};
+use glam::{Mat4, Vec3};
 use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use vulkano::buffer::BufferContents;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::descriptor_set::WriteDescriptorSet;
 use vulkano::device::physical::PhysicalDevice;
 use vulkano::device::{Device, Queue};
 use vulkano::image::view::ImageView;
 use vulkano::image::Image;
 use vulkano::instance::debug::DebugUtilsMessenger;
 use vulkano::instance::Instance;
-use vulkano::pipeline::{GraphicsPipeline, PipelineLayout};
-use vulkano::render_pass::{Framebuffer, RenderPass};
-use vulkano::swapchain::{Surface, Swapchain};
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline, PipelineLayout};
+use vulkano::swapchain::{
+    acquire_next_image, Surface, SurfaceInfo, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo,
+};
+use vulkano::sync::GpuFuture;
+use vulkano::{sync, Validated, VulkanError};
 use winit::event_loop::EventLoop;
 use winit::window::Window;
 
+/// Fields are dropped in declaration order, so objects are declared before what they depend on.
 pub struct AppVulkan {
-    pub instance: Arc<Instance>,
-    pub debug_utils_messenger: Option<DebugUtilsMessenger>,
-    pub surface: Arc<Surface>,
-    pub physical_device: Arc<PhysicalDevice>,
-    pub device: Arc<Device>,
-    pub graphics_queue: Arc<Queue>,
-    pub present_queue: Arc<Queue>,
-    pub swapchain: Arc<Swapchain>,
-    pub swapchain_images: Vec<Arc<Image>>,
-    pub swapchain_image_views: Vec<Arc<ImageView>>,
-    pub render_pass: Arc<RenderPass>,
-    pub pipeline_layout: Arc<PipelineLayout>,
+    /// Pushed to the shaders with every draw, the transform preceded by `view_projection`
+    pub object_push_constants: ObjectPushConstants,
+    /// Maps world space to clip space, the identity unless a camera is set
+    view_projection: Mat4,
+    pub shader_watcher: Option<ShaderWatcher>,
+    pub previous_frame_end: Option<Box<dyn GpuFuture>>,
+    /// Set when the window is resized or presenting reports the swapchain out of date, so it is
+    /// recreated before the next frame
+    pub swapchain_outdated: bool,
+    /// Created with the first emitter
+    pub particles: Option<AppParticles>,
+    /// `None` if the device has no compute queue family
+    pub compute: Option<AppCompute>,
+    pub command_buffer_allocator: StandardCommandBufferAllocator,
     pub graphics_pipeline: Arc<GraphicsPipeline>,
-    pub framebuffers: Vec<Arc<Framebuffer>>,
+    pub pipeline_layout: Arc<PipelineLayout>,
+    pub pipeline_variants: PipelineVariants,
+    pub render_target: RenderTarget,
+    pub pipeline_cache: Arc<PipelineCache>,
+    pub swapchain_image_views: Vec<Arc<ImageView>>,
+    pub swapchain_images: Vec<Arc<Image>>,
+    pub swapchain: Arc<Swapchain>,
+    pub present_queue: Arc<Queue>,
+    pub graphics_queue: Arc<Queue>,
+    pub device: Arc<Device>,
+    _physical_device: Arc<PhysicalDevice>,
+    _surface: Arc<Surface>,
+    _debug_utils_messenger: Option<DebugUtilsMessenger>,
+    _instance: Arc<Instance>,
 }
 
 impl AppVulkan {
     pub fn init(
         event_loop: &EventLoop<()>,
         window: &Arc<Window>,
-        enable_validation: bool,
+        config: &AppConfig,
     ) -> Result<Self> {
-        let instance = create_instance(event_loop, enable_validation)?;
-        let debug_utils_messenger = if enable_validation {
+        let requirements = DeviceRequirements::new(config);
+        let instance = create_instance(
+            event_loop,
+            config.enable_validation,
+            &config.layers,
+            requirements.max_api_version,
+        )?;
+        let debug_utils_messenger = if config.enable_validation {
             Some(setup_debug_messenger(&instance)?)
         } else {
             None
         };
         let surface = create_surface(&instance, window)?;
-        let (physical_device, queue_family_indices, swap_chain_support) =
-            pick_physical_device(&instance, &surface)?;
+        let (physical_device, queue_family_indices, swap_chain_support, capabilities) =
+            pick_physical_device(&instance, &surface, &requirements)?;
+        requirements.log_optional(&capabilities);
         let AppLogicalDevice {
             device,
             graphics_queue,
             present_queue,
-        } = AppLogicalDevice::create(&physical_device, &queue_family_indices)?;
+            compute_queue,
+        } = AppLogicalDevice::create(&physical_device, &queue_family_indices, &capabilities)?;
+        let dynamic_rendering = device.enabled_features().dynamic_rendering;
+        if config.dynamic_rendering && !dynamic_rendering {
+            warn!("device does not support dynamic rendering, falling back to a render pass");
+        }
         let (swapchain, swapchain_images) = swap_chain_support.create_swapchain(
             &device,
             &surface,
             window,
             &queue_family_indices,
+            config,
         )?;
         let swapchain_image_views = create_image_views(&swapchain_images)?;
-        let render_pass = create_render_pass(&device, &swapchain)?;
+        let render_target = RenderTarget::new(
+            &device,
+            &swapchain,
+            &swapchain_image_views,
+            dynamic_rendering,
+        )?;
+        info!(
+            "rendering with {}",
+            if dynamic_rendering {
+                "dynamic rendering"
+            } else {
+                "a render pass"
+            }
+        );
+        let pipeline_cache = load_pipeline_cache(&device)?;
+        let mut pipeline_variants = PipelineVariants::load(
+            &device,
+            &swapchain,
+            &render_target.pipeline_subpass()?,
+            &pipeline_cache,
+            config,
+        )?;
         let (pipeline_layout, graphics_pipeline) =
-            create_graphics_pipeline(&device, &swapchain, &render_pass)?;
-        let framebuffers = create_framebuffers(&render_pass, &swapchain_image_views)?;
-
-        Ok(Self {
-            instance,
-            debug_utils_messenger,
-            surface,
-            physical_device,
-            device,
-            graphics_queue,
-            present_queue,
-            swapchain,
-            swapchain_images,
-            swapchain_image_views,
-            render_pass,
-            pipeline_layout,
+            pipeline_variants.get(config.pipeline_variant.as_deref())?;
+        let command_buffer_allocator =
+            StandardCommandBufferAllocator::new(device.clone(), Default::default());
+        let previous_frame_end = Some(sync::now(device.clone()).boxed());
+        let compute = compute_queue.map(|compute_queue| {
+            AppCompute::new(
+                &device,
+                &compute_queue,
+                &graphics_queue,
+                &pipeline_cache,
+                CompileSettings {
+                    include_dirs: include_dirs(config),
+                    ..CompileSettings::default()
+                },
+            )
+        });
+        match &compute {
+            Some(compute) if compute.is_async() => info!(
+                "compute runs asynchronously on queue family {}",
+                compute.queue().queue_family_index()
+            ),
+            Some(_) => info!("compute shares the graphics queue"),
+            None => warn!("device has no compute queue family, compute is unavailable"),
+        }
+
+        let shader_watcher = config
+            .hot_reload
+            .then(|| ShaderWatcher::new(pipeline_variants.dependencies()));
+
+        let app_vulkan = Self {
+            object_push_constants: ObjectPushConstants::default(),
+            view_projection: Mat4::IDENTITY,
+            shader_watcher,
+            previous_frame_end,
+            swapchain_outdated: false,
+            particles: None,
+            compute,
+            command_buffer_allocator,
             graphics_pipeline,
-            framebuffers,
-        })
+            pipeline_layout,
+            pipeline_variants,
+            pipeline_cache,
+            render_target,
+            swapchain_image_views,
+            swapchain_images,
+            swapchain,
+            present_queue,
+            graphics_queue,
+            device,
+            _physical_device: physical_device,
+            _surface: surface,
+            _debug_utils_messenger: debug_utils_messenger,
+            _instance: instance,
+        };
+        app_vulkan.set_object_names()?;
+
+        Ok(app_vulkan)
+    }
+
+    pub fn draw_frame(&mut self, window: &Window, config: &AppConfig) -> Result<()> {
+        if let Some(previous_frame_end) = self.previous_frame_end.as_mut() {
+            previous_frame_end.cleanup_finished();
+        }
+
+        let window_size = window.inner_size();
+        if window_size.width == 0 || window_size.height == 0 {
+            // Minimized, there is no extent to create a swapchain with
+            return Ok(());
+        }
+        if self.swapchain_outdated && !self.recreate_swapchain(window, config)? {
+            return Ok(());
+        }
+
+        let (image_index, suboptimal, acquire_future) =
+            match acquire_next_image(self.swapchain.clone(), None) {
+                Ok(acquired) => acquired,
+                Err(Validated::Error(VulkanError::OutOfDate)) => {
+                    debug!("swapchain is out of date, skipping frame");
+                    self.swapchain_outdated = true;
+                    return Ok(());
+                }
+                Err(e) => Err(e)?,
+            };
+        if suboptimal {
+            debug!("swapchain is suboptimal, recreating it after this frame");
+            self.swapchain_outdated = true;
+        }
+
+        if let (Some(particles), Some(compute)) = (self.particles.as_mut(), &self.compute) {
+            let after = self
+                .previous_frame_end
+                .take()
+                .unwrap_or_else(|| sync::now(self.device.clone()).boxed());
+            self.previous_frame_end = Some(particles.update(compute, after)?);
+        }
+
+        let command_buffer = record_command_buffer(
+            &self.command_buffer_allocator,
+            &self.graphics_queue,
+            &self.render_target,
+            image_index,
+            &self.graphics_pipeline,
+            ObjectPushConstants {
+                transform: (self.view_projection
+                    * Mat4::from_cols_array_2d(&self.object_push_constants.transform))
+                .to_cols_array_2d(),
+                ..self.object_push_constants
+            },
+            self.particles.as_ref(),
+        )?;
+
+        let _label = QueueLabel::begin(&self.graphics_queue, "submit frame", SUBMIT_COLOR)?;
+        let future = self
+            .previous_frame_end
+            .take()
+            .unwrap_or_else(|| sync::now(self.device.clone()).boxed())
+            .join(acquire_future)
+            .then_execute(self.graphics_queue.clone(), command_buffer)?
+            .then_swapchain_present(
+                self.present_queue.clone(),
+                SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_index),
+            )
+            .then_signal_fence_and_flush();
+
+        self.previous_frame_end = match future.map_err(Validated::unwrap) {
+            Ok(future) => Some(future.boxed()),
+            Err(VulkanError::OutOfDate) => {
+                debug!("swapchain is out of date after present");
+                self.swapchain_outdated = true;
+                None
+            }
+            Err(e) => Err(e)?,
+        };
+
+        Ok(())
+    }
+
+    /// Recreates the swapchain with `image_extent`, along with the attachments and the
+    /// pipelines built for its extent.
+    /// Returns whether the swapchain was recreated. It is not while the surface has no extent, or
+    /// if it changes again while recreating, which can happen during an interactive resize; the
+    /// frame is skipped and the next one tries again.
+    fn recreate_swapchain(&mut self, window: &Window, config: &AppConfig) -> Result<bool> {
+        let surface_capabilities = self
+            .device
+            .physical_device()
+            .surface_capabilities(self.swapchain.surface(), SurfaceInfo::default())?;
+        let image_extent = choose_swap_extent(&surface_capabilities, window);
+        if image_extent.contains(&0) {
+            debug!("surface has no extent, skipping frame");
+            return Ok(false);
+        }
+        let recreated = self.swapchain.recreate(SwapchainCreateInfo {
+            image_extent,
+            ..self.swapchain.create_info()
+        });
+        let (swapchain, swapchain_images) = match recreated {
+            Ok(recreated) => recreated,
+            Err(Validated::Error(VulkanError::OutOfDate)) => {
+                debug!("surface changed while recreating the swapchain, skipping frame");
+                return Ok(false);
+            }
+            Err(e) => Err(e)?,
+        };
+        let swapchain_image_views = create_image_views(&swapchain_images)?;
+        self.render_target.resize(&swapchain_image_views)?;
+        self.pipeline_variants.set_swapchain(&swapchain);
+        (self.pipeline_layout, self.graphics_pipeline) = self
+            .pipeline_variants
+            .get(config.pipeline_variant.as_deref())?;
+        self.swapchain = swapchain;
+        self.swapchain_images = swapchain_images;
+        self.swapchain_image_views = swapchain_image_views;
+        self.swapchain_outdated = false;
+        info!("swapchain recreated with extent {image_extent:?}");
+
+        set_object_name(&self.swapchain, "swapchain")?;
+        set_object_names(&self.swapchain_images, "swapchain image")?;
+        set_object_names(&self.swapchain_image_views, "swapchain image view")?;
+        self.render_target.set_object_names()?;
+        Ok(true)
+    }
+
+    /// Advances the particles by a step of `delta_time` seconds, simulated with the next frame.
+    pub fn step_particles(&mut self, delta_time: f32) {
+        if let Some(particles) = &mut self.particles {
+            particles.step(delta_time);
+        }
+    }
+
+    /// Sets the matrix mapping world space to clip space for the scene and the particles.
+    ///
+    /// The embedded triangle is written in clip space, with +Y down the window, so it is mirrored
+    /// to stand upright in a world with +Y up, which also keeps its front face clockwise.
+    pub fn set_view_projection(&mut self, view_projection: Mat4) {
+        self.view_projection = view_projection * Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+        if let Some(particles) = &mut self.particles {
+            particles.view_projection = view_projection.to_cols_array_2d();
+        }
+    }
+
+    pub fn compute(&self) -> Result<&AppCompute> {
+        Ok(self.compute.as_ref().ok_or(AppError::ComputeUnavailable)?)
+    }
+
+    /// Dispatches compute work after the previous frame and before the next one.
+    pub fn dispatch<Pc: BufferContents>(
+        &mut self,
+        pipeline: &Arc<ComputePipeline>,
+        descriptor_writes: impl IntoIterator<Item = WriteDescriptorSet>,
+        push_constants: Pc,
+        group_counts: [u32; 3],
+    ) -> Result<()> {
+        // Borrows the field rather than `self`, which is borrowed mutably below
+        let compute = self.compute.as_ref().ok_or(AppError::ComputeUnavailable)?;
+        let after = self
+            .previous_frame_end
+            .take()
+            .unwrap_or_else(|| sync::now(self.device.clone()).boxed());
+        self.previous_frame_end = Some(compute.dispatch(
+            after,
+            pipeline,
+            descriptor_writes,
+            push_constants,
+            group_counts,
+        )?);
+        Ok(())
+    }
+
+    pub fn add_emitter(&mut self, config: EmitterConfig) -> Result<EmitterId> {
+        let compute = self.compute.as_ref().ok_or(AppError::ComputeUnavailable)?;
+        let particles = match &mut self.particles {
+            Some(particles) => particles,
+            particles => particles.insert(AppParticles::new(
+                &self.device,
+                &self.render_target.pipeline_subpass()?,
+                &self.pipeline_cache,
+                compute,
+                OutputTransfer::of(&self.swapchain),
+            )?),
+        };
+        particles.add_emitter(compute, config)
+    }
+
+    pub fn set_emitter(&mut self, id: EmitterId, config: EmitterConfig) -> Result<()> {
+        let compute = self.compute.as_ref().ok_or(AppError::ComputeUnavailable)?;
+        match &mut self.particles {
+            Some(particles) => particles.set_emitter(compute, id, config),
+            None => bail!("no particle emitter {id:?}"),
+        }
+    }
+
+    pub fn remove_emitter(&mut self, id: EmitterId) -> bool {
+        self.particles
+            .as_mut()
+            .is_some_and(|particles| particles.remove_emitter(id))
+    }
+
+    /// Rebuilds the graphics pipeline if a file it is built from changed since the last call.
+    ///
+    /// Keeps the current pipeline if the new one can not be built.
+    pub fn reload_changed_shaders(&mut self, config: &AppConfig) {
+        let Some(shader_watcher) = self.shader_watcher.as_mut() else {
+            return;
+        };
+        let changed_files = shader_watcher.changed_files();
+        if changed_files.is_empty() {
+            return;
+        }
+        let dependencies = self.pipeline_variants.dependencies();
+        let changed_dependencies: Vec<_> = changed_files
+            .iter()
+            .map(|path| canonical(path))
+            .filter(|path| dependencies.contains(path))
+            .collect();
+        if changed_dependencies.is_empty() {
+            debug!("changed files {changed_files:?} are not used by the graphics pipeline");
+            return;
+        }
+        info!("shader files changed: {changed_dependencies:?}");
+
+        match self.rebuild_graphics_pipeline(config) {
+            Ok(()) => {
+                info!("graphics pipeline rebuilt");
+                if let Some(shader_watcher) = self.shader_watcher.as_mut() {
+                    shader_watcher.watch_files(&self.pipeline_variants.dependencies());
+                }
+            }
+            Err(e) => match e.downcast_ref::<AppError>() {
+                Some(AppError::ShaderCompilation {
+                    shader,
+                    diagnostics,
+                }) => {
+                    error!("can not compile {shader}, keeping previous graphics pipeline");
+                    for line in diagnostics.lines() {
+                        error!("{line}");
+                    }
+                }
+                _ => error!("can not rebuild graphics pipeline, keeping previous one: {e:?}"),
+            },
+        }
+    }
+
+    fn rebuild_graphics_pipeline(&mut self, config: &AppConfig) -> Result<()> {
+        let mut pipeline_variants = PipelineVariants::load(
+            &self.device,
+            &self.swapchain,
+            &self.render_target.pipeline_subpass()?,
+            &self.pipeline_cache,
+            config,
+        )?;
+        let (pipeline_layout, graphics_pipeline) =
+            pipeline_variants.get(config.pipeline_variant.as_deref())?;
+        self.pipeline_variants = pipeline_variants;
+        self.pipeline_layout = pipeline_layout;
+        self.graphics_pipeline = graphics_pipeline;
+        Ok(())
+    }
+
+    #[inline]
+    pub fn wait_idle(&self) -> Result<()> {
+        // SAFETY: queues are only submitted to from the thread running the event loop
+        unsafe { self.device.wait_idle()? };
+        Ok(())
+    }
+
+    fn set_object_names(&self) -> Result<()> {
+        set_object_name(&DeviceObject(&self.device), "device")?;
+        if Arc::ptr_eq(&self.graphics_queue, &self.present_queue) {
+            set_object_name(&self.graphics_queue, "graphics/present queue")?;
+        } else {
+            set_object_name(&self.graphics_queue, "graphics queue")?;
+            set_object_name(&self.present_queue, "present queue")?;
+        }
+        if let Some(compute) = &self.compute {
+            let compute_queue = compute.queue();
+            if !Arc::ptr_eq(compute_queue, &self.graphics_queue)
+                && !Arc::ptr_eq(compute_queue, &self.present_queue)
+            {
+                set_object_name(compute_queue, "compute queue")?;
+            }
+        }
+        set_object_name(&self.swapchain, "swapchain")?;
+        set_object_names(&self.swapchain_images, "swapchain image")?;
+        set_object_names(&self.swapchain_image_views, "swapchain image view")?;
+        self.render_target.set_object_names()?;
+        set_object_name(&self.pipeline_cache, "pipeline cache")?;
+        Ok(())
+    }
+}
+
+impl Drop for AppVulkan {
+    fn drop(&mut self) {
+        self.previous_frame_end = None;
+        if let Err(e) = self.wait_idle() {
+            error!("can not wait for device to become idle: {e}");
+        }
+        if let Err(e) = save_pipeline_cache(&self.pipeline_cache) {
+            warn!("can not save pipeline cache: {e:?}");
+        }
     }
 }
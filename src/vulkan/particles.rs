@@ -0,0 +1,398 @@
+use crate::particles::{EmitterConfig, EmitterId};
+use crate::vulkan::compute::AppCompute;
+use crate::vulkan::debug::set_object_name;
+use crate::vulkan::debug_label::{CommandBufferLabel, PARTICLES_COLOR};
+use crate::vulkan::pipeline_description::BlendMode;
+use crate::vulkan::push_constants::push_constants;
+use crate::vulkan::render_target::color_attachment_count;
+use crate::vulkan::shader_loader::entry_point;
+use crate::vulkan::specialization::specialize;
+use crate::vulkan::surface_format::OutputTransfer;
+use anyhow::{anyhow, bail, Result};
+use smallvec::smallvec;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::info;
+use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::multisample::MultisampleState;
+use vulkano::pipeline::graphics::rasterization::RasterizationState;
+use vulkano::pipeline::graphics::subpass::PipelineSubpassType;
+use vulkano::pipeline::graphics::vertex_input::VertexInputState;
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport, ViewportState};
+use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{
+    ComputePipeline, DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+    PipelineShaderStageCreateInfo,
+};
+use vulkano::shader::ShaderStage;
+use vulkano::sync::GpuFuture;
+
+mod shaders {
+    vulkano_shaders::shader! {
+        vulkan_version: "1.2",
+        spirv_version: "1.6",
+        include: ["shaders/include"],
+        shaders: {
+            update: {
+                ty: "compute",
+                path: "shaders/particles/update.comp",
+            },
+            vertex: {
+                ty: "vertex",
+                path: "shaders/particles/particle.vert",
+            },
+            fragment: {
+                ty: "fragment",
+                path: "shaders/particles/particle.frag",
+            }
+        }
+    }
+}
+
+/// Work group size of `shaders/particles/update.comp`.
+const UPDATE_GROUP_SIZE: u32 = 64;
+
+/// Most steps kept for the next frame; older ones are dropped while no frames are drawn, e.g.
+/// while the window is minimized.
+const MAX_PENDING_STEPS: usize = 64;
+
+/// Vertices of the quad drawn for each particle.
+const QUAD_VERTICES: u32 = 6;
+
+/// Laid out like `Particle` in `shaders/particles/particle.glsl`.
+#[derive(BufferContents, Clone, Copy, Default)]
+#[repr(C)]
+struct Particle {
+    position: [f32; 4],
+    velocity: [f32; 4],
+}
+
+/// Laid out like the `EmitterPushConstants` block of `shaders/particles/update.comp`.
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct EmitterPushConstants {
+    origin: [f32; 4],
+    velocity: [f32; 4],
+    gravity: [f32; 4],
+    attractor: [f32; 4],
+    lifetime: f32,
+    delta_time: f32,
+    seed: u32,
+    spawn_start: u32,
+    spawn_count: u32,
+}
+
+/// Laid out like the `DrawPushConstants` block of `shaders/particles/particle.vert`.
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct DrawPushConstants {
+    view_projection: [[f32; 4]; 4],
+    start_color: [f32; 4],
+    end_color: [f32; 4],
+    start_size: f32,
+    end_size: f32,
+}
+
+struct Emitter {
+    config: EmitterConfig,
+    particles: Subbuffer<[Particle]>,
+    /// Binds `particles` to the draw pipeline
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    /// Slot the next spawned particle goes to
+    next_slot: u32,
+    /// Fraction of a particle left to spawn from previous updates
+    pending_spawns: f32,
+}
+
+impl Emitter {
+    /// Spawn range and forces for an update advancing the particles by `delta_time`.
+    fn update_push_constants(&mut self, delta_time: f32, seed: u32) -> EmitterPushConstants {
+        let config = &self.config;
+        self.pending_spawns += config.spawn_rate * delta_time;
+        let spawn_count = (self.pending_spawns as u32).min(config.capacity);
+        self.pending_spawns = self.pending_spawns.fract();
+        let spawn_start = self.next_slot;
+        self.next_slot = (self.next_slot + spawn_count) % config.capacity;
+
+        EmitterPushConstants {
+            origin: config.origin.extend(config.spawn_radius).to_array(),
+            velocity: config.velocity.extend(config.velocity_spread).to_array(),
+            gravity: config.gravity.extend(config.drag).to_array(),
+            attractor: config
+                .attractor
+                .extend(config.attractor_strength)
+                .to_array(),
+            lifetime: config.lifetime,
+            delta_time,
+            seed,
+            spawn_start,
+            spawn_count,
+        }
+    }
+}
+
+/// Particle emitters, advanced by a compute shader and drawn as additive quads.
+///
+/// The particles of an emitter live in a storage buffer shared by the compute and graphics
+/// queues, so they never round-trip through the host.
+pub struct AppParticles {
+    /// Maps particle positions to clip space
+    pub view_projection: [[f32; 4]; 4],
+    emitters: BTreeMap<EmitterId, Emitter>,
+    next_id: u64,
+    /// Changed with every update, so respawned particles get new random values
+    seed: u32,
+    /// Time steps to simulate with the next frame, in seconds
+    pending_steps: Vec<f32>,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    draw_pipeline: Arc<GraphicsPipeline>,
+    update_pipeline: Arc<ComputePipeline>,
+}
+
+impl AppParticles {
+    pub fn new(
+        device: &Arc<Device>,
+        subpass: &PipelineSubpassType,
+        pipeline_cache: &Arc<PipelineCache>,
+        compute: &AppCompute,
+        output_transfer: OutputTransfer,
+    ) -> Result<Self> {
+        let update = entry_point(
+            &shaders::load_update(device.clone())?,
+            "embedded particle update shader",
+            "main",
+            ShaderStage::Compute,
+        )?;
+        Ok(Self {
+            view_projection: glam::Mat4::IDENTITY.to_cols_array_2d(),
+            emitters: BTreeMap::new(),
+            next_id: 0,
+            seed: 0,
+            pending_steps: Vec::new(),
+            descriptor_set_allocator: StandardDescriptorSetAllocator::new(
+                device.clone(),
+                Default::default(),
+            ),
+            memory_allocator: Arc::new(StandardMemoryAllocator::new_default(device.clone())),
+            draw_pipeline: create_draw_pipeline(device, subpass, pipeline_cache, output_transfer)?,
+            update_pipeline: compute.create_pipeline(update)?,
+        })
+    }
+
+    pub fn add_emitter(
+        &mut self,
+        compute: &AppCompute,
+        config: EmitterConfig,
+    ) -> Result<EmitterId> {
+        let id = EmitterId(self.next_id);
+        self.next_id += 1;
+        let emitter = self.create_emitter(compute, config)?;
+        info!(
+            "added particle emitter {id:?} with {} particles",
+            emitter.config.capacity
+        );
+        self.emitters.insert(id, emitter);
+        Ok(id)
+    }
+
+    /// Replaces the config of an emitter, respawning its particles if the capacity changes.
+    pub fn set_emitter(
+        &mut self,
+        compute: &AppCompute,
+        id: EmitterId,
+        config: EmitterConfig,
+    ) -> Result<()> {
+        let Some(emitter) = self.emitters.get_mut(&id) else {
+            bail!("no particle emitter {id:?}");
+        };
+        if emitter.config.capacity == config.capacity {
+            emitter.config = config;
+        } else {
+            let emitter = self.create_emitter(compute, config)?;
+            self.emitters.insert(id, emitter);
+        }
+        Ok(())
+    }
+
+    /// Returns whether the emitter existed.
+    pub fn remove_emitter(&mut self, id: EmitterId) -> bool {
+        self.emitters.remove(&id).is_some()
+    }
+
+    /// Queues a simulation step of `delta_time` seconds, submitted with the next frame.
+    pub fn step(&mut self, delta_time: f32) {
+        if self.pending_steps.len() == MAX_PENDING_STEPS {
+            self.pending_steps.remove(0);
+        }
+        self.pending_steps.push(delta_time);
+    }
+
+    /// Submits the compute work advancing every emitter by the steps queued since the last
+    /// update.
+    pub fn update(
+        &mut self,
+        compute: &AppCompute,
+        mut after: Box<dyn GpuFuture>,
+    ) -> Result<Box<dyn GpuFuture>> {
+        for delta_time in self.pending_steps.drain(..) {
+            for emitter in self.emitters.values_mut() {
+                self.seed = self.seed.wrapping_add(1);
+                let constants = emitter.update_push_constants(delta_time, self.seed);
+                after = compute.dispatch(
+                    after,
+                    &self.update_pipeline,
+                    [WriteDescriptorSet::buffer(0, emitter.particles.clone())],
+                    constants,
+                    [emitter.config.capacity.div_ceil(UPDATE_GROUP_SIZE), 1, 1],
+                )?;
+            }
+        }
+        Ok(after)
+    }
+
+    /// Records the draws of every emitter into a render pass instance.
+    pub fn draw<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        viewport: &Viewport,
+        scissor: &Scissor,
+    ) -> Result<()> {
+        if self.emitters.is_empty() {
+            return Ok(());
+        }
+        let mut builder = CommandBufferLabel::begin(builder, "particles", PARTICLES_COLOR)?;
+        builder
+            .bind_pipeline_graphics(self.draw_pipeline.clone())?
+            .set_viewport(0, smallvec![viewport.clone()])?
+            .set_scissor(0, smallvec![*scissor])?;
+        for emitter in self.emitters.values() {
+            let config = &emitter.config;
+            builder.bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.draw_pipeline.layout().clone(),
+                0,
+                emitter.descriptor_set.clone(),
+            )?;
+            push_constants(
+                &mut builder,
+                self.draw_pipeline.layout(),
+                DrawPushConstants {
+                    view_projection: self.view_projection,
+                    start_color: config.start_color.to_array(),
+                    end_color: config.end_color.to_array(),
+                    start_size: config.start_size,
+                    end_size: config.end_size,
+                },
+            )?;
+            builder.draw(QUAD_VERTICES, config.capacity, 0, 0)?;
+        }
+        Ok(())
+    }
+
+    fn create_emitter(&self, compute: &AppCompute, config: EmitterConfig) -> Result<Emitter> {
+        if config.capacity == 0 {
+            bail!("particle emitter capacity must be positive");
+        }
+        // Zeroed particles have a lifetime of 0, so every slot starts free
+        let particles = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                sharing: compute.sharing(),
+                ..BufferCreateInfo::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..AllocationCreateInfo::default()
+            },
+            (0..config.capacity).map(|_| Particle::default()),
+        )?;
+        set_object_name(particles.buffer(), "particle buffer")?;
+        let set_layout = self
+            .draw_pipeline
+            .layout()
+            .set_layouts()
+            .first()
+            .ok_or_else(|| anyhow!("particle vertex shader declares no descriptor set"))?;
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            set_layout.clone(),
+            [WriteDescriptorSet::buffer(0, particles.clone())],
+            [],
+        )?;
+        Ok(Emitter {
+            config,
+            particles,
+            descriptor_set,
+            next_slot: 0,
+            pending_spawns: 0.0,
+        })
+    }
+}
+
+fn create_draw_pipeline(
+    device: &Arc<Device>,
+    subpass: &PipelineSubpassType,
+    pipeline_cache: &Arc<PipelineCache>,
+    output_transfer: OutputTransfer,
+) -> Result<Arc<GraphicsPipeline>> {
+    let specialization = output_transfer.specialization();
+    let load_stage = |module, shader: &str, stage| -> Result<_> {
+        Ok(PipelineShaderStageCreateInfo::new(specialize(
+            &entry_point(&module, shader, "main", stage)?,
+            &specialization,
+        )?))
+    };
+    let stages = smallvec![
+        load_stage(
+            shaders::load_vertex(device.clone())?,
+            "embedded particle vertex shader",
+            ShaderStage::Vertex,
+        )?,
+        load_stage(
+            shaders::load_fragment(device.clone())?,
+            "embedded particle fragment shader",
+            ShaderStage::Fragment,
+        )?,
+    ];
+    let pipeline_layout = PipelineLayout::new(
+        device.clone(),
+        PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+            .into_pipeline_layout_create_info(device.clone())?,
+    )?;
+
+    Ok(GraphicsPipeline::new(
+        device.clone(),
+        Some(pipeline_cache.clone()),
+        GraphicsPipelineCreateInfo {
+            stages,
+            // Particles are read from the storage buffer by instance index
+            vertex_input_state: Some(VertexInputState::new()),
+            input_assembly_state: Some(InputAssemblyState::default()),
+            viewport_state: Some(ViewportState::default()),
+            rasterization_state: Some(RasterizationState::default()),
+            multisample_state: Some(MultisampleState::default()),
+            color_blend_state: Some(ColorBlendState::with_attachment_states(
+                color_attachment_count(subpass),
+                BlendMode::Additive.attachment_state(),
+            )),
+            dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                .into_iter()
+                .collect(),
+            subpass: Some(subpass.clone()),
+            ..GraphicsPipelineCreateInfo::layout(pipeline_layout)
+        },
+    )?)
+}
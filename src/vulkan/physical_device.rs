@@ -1,63 +1,90 @@
-use crate::app_error::AppError;
+use crate::app_error::{AppError, DeviceRejection, RejectedDevice};
+use crate::vulkan::device_requirements::{DeviceCapabilities, DeviceRequirements};
 use crate::vulkan::swapchain::SwapChainSupportDetails;
 use crate::vulkan::QueueFamilyIndices;
 use anyhow::Result;
 use std::sync::Arc;
 use tracing::warn;
 use vulkano::device::physical::PhysicalDevice;
-use vulkano::device::DeviceExtensions;
 use vulkano::instance::Instance;
 use vulkano::swapchain::Surface;
 
 pub fn pick_physical_device(
     instance: &Arc<Instance>,
     surface: &Surface,
+    requirements: &DeviceRequirements,
 ) -> Result<(
     Arc<PhysicalDevice>,
     QueueFamilyIndices,
     SwapChainSupportDetails,
+    DeviceCapabilities,
 )> {
-    Ok(instance
-        .enumerate_physical_devices()?
-        .filter(|physical_device| {
-            physical_device
-                .supported_extensions()
-                .contains(&DEVICE_EXTENSIONS)
-        })
-        .find_map(
-            |physical_device| match QueueFamilyIndices::find(&physical_device, surface) {
-                Ok(Some(queue_family_indices)) => {
-                    match SwapChainSupportDetails::query(&physical_device, surface) {
-                        Ok(swap_chain_support) => swap_chain_support
-                            .is_adequate()
-                            .then_some(queue_family_indices)
-                            .map(|queue_family_indices| {
-                                (physical_device, queue_family_indices, swap_chain_support)
-                            }),
-                        Err(e) => {
-                            warn!(
-                                "can not query swapchain support \
-                                 for physical device [{physical_device:?}], \
-                                 with err: {e}, skipping it"
-                            );
-                            None
-                        }
-                    }
-                }
-                Ok(None) => None,
-                Err(e) => {
-                    warn!(
-                        "can not find queue families for device [{physical_device:?}], \
-                         with last err: {e}, skipping it"
-                    );
-                    None
-                }
-            },
-        )
-        .ok_or(AppError::PhysicalDevices)?)
+    let mut rejected_devices = Vec::new();
+    for physical_device in instance.enumerate_physical_devices()? {
+        match check_physical_device(&physical_device, surface, requirements) {
+            Ok((queue_family_indices, swap_chain_support, capabilities)) => {
+                return Ok((
+                    physical_device,
+                    queue_family_indices,
+                    swap_chain_support,
+                    capabilities,
+                ));
+            }
+            Err(reasons) => {
+                let rejected_device = RejectedDevice {
+                    name: physical_device.properties().device_name.clone(),
+                    reasons,
+                };
+                warn!("skipping physical device {rejected_device}");
+                rejected_devices.push(rejected_device);
+            }
+        }
+    }
+    Err(AppError::PhysicalDevices(rejected_devices))?
 }
 
-pub const DEVICE_EXTENSIONS: DeviceExtensions = DeviceExtensions {
-    khr_swapchain: true,
-    ..DeviceExtensions::empty()
-};
+fn check_physical_device(
+    physical_device: &PhysicalDevice,
+    surface: &Surface,
+    requirements: &DeviceRequirements,
+) -> Result<
+    (
+        QueueFamilyIndices,
+        SwapChainSupportDetails,
+        DeviceCapabilities,
+    ),
+    Vec<DeviceRejection>,
+> {
+    // Every check runs, so a rejected device lists all it lacks
+    let capabilities = requirements.negotiate(physical_device);
+
+    let queue_family_indices = QueueFamilyIndices::find(physical_device, surface);
+
+    let swap_chain_support = SwapChainSupportDetails::query(physical_device, surface)
+        .map_err(|e| {
+            vec![DeviceRejection::QueryFailed(format!(
+                "can not query swapchain support: {e}"
+            ))]
+        })
+        .and_then(|swap_chain_support| {
+            let inadequacies = swap_chain_support.inadequacies();
+            if inadequacies.is_empty() {
+                Ok(swap_chain_support)
+            } else {
+                Err(inadequacies)
+            }
+        });
+
+    match (capabilities, queue_family_indices, swap_chain_support) {
+        (Ok(capabilities), Ok(queue_family_indices), Ok(swap_chain_support)) => {
+            Ok((queue_family_indices, swap_chain_support, capabilities))
+        }
+        (capabilities, queue_family_indices, swap_chain_support) => Err(capabilities
+            .err()
+            .into_iter()
+            .chain(queue_family_indices.err())
+            .chain(swap_chain_support.err())
+            .flatten()
+            .collect()),
+    }
+}
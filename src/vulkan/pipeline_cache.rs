@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::pipeline::cache::{PipelineCache, PipelineCacheCreateInfo};
+
+/// `VkPipelineCacheHeaderVersionOne`: header size, header version, vendor ID, device ID and
+/// pipeline cache UUID, all stored least significant byte first.
+const HEADER_SIZE: usize = 32;
+const HEADER_VERSION_ONE: u32 = 1;
+
+/// Creates a pipeline cache, prefilled with the data saved for this device by a previous run.
+pub fn load_pipeline_cache(device: &Arc<Device>) -> Result<Arc<PipelineCache>> {
+    let initial_data = pipeline_cache_path(device.physical_device())
+        .and_then(|path| read_pipeline_cache(&path, device.physical_device()))
+        .unwrap_or_default();
+
+    // SAFETY: the header of the data is checked to be written for this device and driver,
+    // the rest of it comes from `PipelineCache::get_data`
+    Ok(unsafe {
+        PipelineCache::new(
+            device.clone(),
+            PipelineCacheCreateInfo {
+                initial_data,
+                ..PipelineCacheCreateInfo::default()
+            },
+        )
+    }?)
+}
+
+/// Writes the pipeline cache data to a temporary file and renames it over the previous one.
+pub fn save_pipeline_cache(pipeline_cache: &PipelineCache) -> Result<()> {
+    let Some(path) = pipeline_cache_path(pipeline_cache.device().physical_device()) else {
+        warn!("no cache directory found, pipeline cache is not saved");
+        return Ok(());
+    };
+    let data = pipeline_cache.get_data()?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("can not create {}", dir.display()))?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, &data).with_context(|| format!("can not write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, &path).with_context(|| {
+        format!(
+            "can not rename {} to {}",
+            tmp_path.display(),
+            path.display()
+        )
+    })?;
+
+    info!(
+        "saved {} bytes of pipeline cache to {}",
+        data.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn read_pipeline_cache(path: &Path, physical_device: &PhysicalDevice) -> Option<Vec<u8>> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            debug!("no pipeline cache at {}", path.display());
+            return None;
+        }
+        Err(e) => {
+            warn!("can not read pipeline cache {}: {e}", path.display());
+            return None;
+        }
+    };
+
+    match validate_header(&data, physical_device) {
+        Ok(()) => {
+            info!(
+                "loaded {} bytes of pipeline cache from {}",
+                data.len(),
+                path.display()
+            );
+            Some(data)
+        }
+        Err(reason) => {
+            warn!("discarding pipeline cache {}: {reason}", path.display());
+            None
+        }
+    }
+}
+
+fn validate_header(data: &[u8], physical_device: &PhysicalDevice) -> Result<(), String> {
+    if data.len() < HEADER_SIZE {
+        return Err(format!("{} bytes is too short for a header", data.len()));
+    }
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().expect("4 bytes slice"))
+    };
+
+    let header_size = read_u32(0) as usize;
+    if header_size < HEADER_SIZE || header_size > data.len() {
+        return Err(format!("invalid header size {header_size}"));
+    }
+    let header_version = read_u32(4);
+    if header_version != HEADER_VERSION_ONE {
+        return Err(format!("unknown header version {header_version}"));
+    }
+
+    let properties = physical_device.properties();
+    let (vendor_id, device_id) = (read_u32(8), read_u32(12));
+    if (vendor_id, device_id) != (properties.vendor_id, properties.device_id) {
+        return Err(format!(
+            "written for device {vendor_id:04x}:{device_id:04x}, not {:04x}:{:04x}",
+            properties.vendor_id, properties.device_id
+        ));
+    }
+    if data[16..HEADER_SIZE] != properties.pipeline_cache_uuid {
+        return Err("pipeline cache UUID mismatch, driver has changed".to_string());
+    }
+    Ok(())
+}
+
+fn pipeline_cache_path(physical_device: &PhysicalDevice) -> Option<PathBuf> {
+    let properties = physical_device.properties();
+    Some(cache_dir()?.join(format!(
+        "pipeline-cache-{:04x}-{:04x}.bin",
+        properties.vendor_id, properties.device_id
+    )))
+}
+
+/// Per-user cache directory of the application, following each platform's convention.
+fn cache_dir() -> Option<PathBuf> {
+    let env_dir = |name| {
+        env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    };
+    let base_dir = if cfg!(windows) {
+        env_dir("LOCALAPPDATA")?
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME")?.join("Library").join("Caches")
+    } else {
+        env_dir("XDG_CACHE_HOME").or_else(|| Some(env_dir("HOME")?.join(".cache")))?
+    };
+    Some(base_dir.join(env!("CARGO_PKG_NAME")))
+}
@@ -0,0 +1,692 @@
+use crate::app_error::AppError;
+use crate::config::AppConfig;
+use crate::toml_section::{parse_document, Section};
+use crate::vulkan::specialization::{Specialization, SpecializationValue};
+use ahash::{HashMap, HashSet};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{TableLike, Value};
+use tracing::info;
+use vulkano::device::Features;
+use vulkano::format::Format;
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::rasterization::{
+    CullMode, FrontFace, PolygonMode, RasterizationState,
+};
+use vulkano::pipeline::graphics::vertex_input::{
+    VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate,
+    VertexInputState,
+};
+use vulkano::pipeline::DynamicState;
+
+/// Description of the pipeline drawing the triangle of the embedded shaders.
+const BUILTIN_DESCRIPTION: &str = include_str!("../../pipelines/triangle.toml");
+const BUILTIN_SOURCE: &str = "built-in pipeline description";
+
+/// Directory the shader paths of the built-in description are relative to.
+const BUILTIN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/pipelines");
+
+const TOPOLOGIES: &[(&str, PrimitiveTopology)] = &[
+    ("point_list", PrimitiveTopology::PointList),
+    ("line_list", PrimitiveTopology::LineList),
+    ("line_strip", PrimitiveTopology::LineStrip),
+    ("triangle_list", PrimitiveTopology::TriangleList),
+    ("triangle_strip", PrimitiveTopology::TriangleStrip),
+    ("triangle_fan", PrimitiveTopology::TriangleFan),
+];
+
+const CULL_MODES: &[(&str, CullMode)] = &[
+    ("none", CullMode::None),
+    ("front", CullMode::Front),
+    ("back", CullMode::Back),
+    ("front_and_back", CullMode::FrontAndBack),
+];
+
+const FRONT_FACES: &[(&str, FrontFace)] = &[
+    ("clockwise", FrontFace::Clockwise),
+    ("counter_clockwise", FrontFace::CounterClockwise),
+];
+
+const POLYGON_MODES: &[(&str, PolygonMode)] = &[
+    ("fill", PolygonMode::Fill),
+    ("line", PolygonMode::Line),
+    ("point", PolygonMode::Point),
+];
+
+const BLEND_MODES: &[(&str, BlendMode)] = &[
+    ("opaque", BlendMode::Opaque),
+    ("alpha", BlendMode::Alpha),
+    ("additive", BlendMode::Additive),
+];
+
+const INPUT_RATES: &[(&str, VertexInputRate)] = &[
+    ("vertex", VertexInputRate::Vertex),
+    ("instance", VertexInputRate::Instance { divisor: 1 }),
+];
+
+/// Dynamic states `record_command_buffer` sets a value for; a pipeline declaring any other would
+/// fail validation at the first draw.
+const DYNAMIC_STATES: &[(&str, DynamicState)] = &[
+    ("viewport", DynamicState::Viewport),
+    ("scissor", DynamicState::Scissor),
+];
+
+/// Formats usable for vertex attributes, named like the Vulkan formats without the prefix.
+const VERTEX_FORMATS: &[(&str, Format)] = &[
+    ("r32_sfloat", Format::R32_SFLOAT),
+    ("r32g32_sfloat", Format::R32G32_SFLOAT),
+    ("r32g32b32_sfloat", Format::R32G32B32_SFLOAT),
+    ("r32g32b32a32_sfloat", Format::R32G32B32A32_SFLOAT),
+    ("r32_sint", Format::R32_SINT),
+    ("r32g32_sint", Format::R32G32_SINT),
+    ("r32g32b32_sint", Format::R32G32B32_SINT),
+    ("r32g32b32a32_sint", Format::R32G32B32A32_SINT),
+    ("r32_uint", Format::R32_UINT),
+    ("r32g32_uint", Format::R32G32_UINT),
+    ("r32g32b32_uint", Format::R32G32B32_UINT),
+    ("r32g32b32a32_uint", Format::R32G32B32A32_UINT),
+    ("r16g16_sfloat", Format::R16G16_SFLOAT),
+    ("r16g16b16a16_sfloat", Format::R16G16B16A16_SFLOAT),
+    ("r8g8b8a8_unorm", Format::R8G8B8A8_UNORM),
+    ("r8g8b8a8_snorm", Format::R8G8B8A8_SNORM),
+    ("r8g8b8a8_uint", Format::R8G8B8A8_UINT),
+    ("a2b10g10r10_unorm_pack32", Format::A2B10G10R10_UNORM_PACK32),
+];
+
+#[derive(Debug, Clone, Copy)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    Additive,
+}
+
+impl BlendMode {
+    pub fn attachment_state(self) -> ColorBlendAttachmentState {
+        let blend = match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some(AttachmentBlend::alpha()),
+            BlendMode::Additive => Some(AttachmentBlend::additive()),
+        };
+        ColorBlendAttachmentState {
+            blend,
+            ..ColorBlendAttachmentState::default()
+        }
+    }
+}
+
+/// Shader stages and fixed function state of a graphics pipeline, read from a TOML file.
+///
+/// `pipelines/triangle.toml` is the built-in description and documents the format.
+#[derive(Debug, Clone)]
+pub struct PipelineDescription {
+    /// Name of the file the description was read from, for error messages
+    pub source: String,
+    /// Path of the description file, if not built in
+    pub path: Option<PathBuf>,
+    pub vertex_shader: Option<PathBuf>,
+    pub fragment_shader: Option<PathBuf>,
+    pub entry_point: Option<String>,
+    pub vertex_input: VertexInputState,
+    pub input_assembly: InputAssemblyState,
+    pub rasterization: RasterizationState,
+    /// One mode for every color attachment, or a mode per attachment
+    pub blend: Vec<BlendMode>,
+    pub dynamic_states: HashSet<DynamicState>,
+    /// Specialization constants shared by all variants
+    pub specialization: Specialization,
+    /// Named variants, each overriding some of the shared specialization constants
+    pub variants: BTreeMap<String, Specialization>,
+    /// Macros defined for the shader sources compiled at runtime
+    pub defines: BTreeMap<String, String>,
+}
+
+impl PipelineDescription {
+    /// Reads the description file given in `config`, or the built-in one.
+    pub fn load(config: &AppConfig) -> Result<Self> {
+        let Some(path) = &config.pipeline else {
+            return Self::parse(BUILTIN_DESCRIPTION, BUILTIN_SOURCE, Path::new(BUILTIN_DIR));
+        };
+        info!("loading pipeline description from {}", path.display());
+        let text =
+            fs::read_to_string(path).with_context(|| format!("can not read {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Ok(Self {
+            path: Some(path.clone()),
+            ..Self::parse(&text, &path.display().to_string(), base_dir)?
+        })
+    }
+
+    /// Builds the color blend state of every color attachment of a subpass.
+    pub fn color_blend_attachments(
+        &self,
+        num_color_attachments: u32,
+    ) -> Result<Vec<ColorBlendAttachmentState>> {
+        let num_color_attachments = num_color_attachments as usize;
+        match self.blend.as_slice() {
+            [mode] => Ok(vec![mode.attachment_state(); num_color_attachments]),
+            modes if modes.len() == num_color_attachments => {
+                Ok(modes.iter().map(|mode| mode.attachment_state()).collect())
+            }
+            modes => Err(self.error(format!(
+                "color_blend.mode: {} modes given for {num_color_attachments} color attachments",
+                modes.len()
+            ))),
+        }
+    }
+
+    /// Specialization constants of the named variant, or the shared ones without a name.
+    pub fn variant_specialization(&self, variant: Option<&str>) -> Result<Specialization> {
+        let mut specialization = self.specialization.clone();
+        if let Some(name) = variant {
+            let overrides = self.variants.get(name).ok_or_else(|| {
+                let names: Vec<_> = self.variants.keys().collect();
+                self.error(format!("unknown variant `{name}`, expected one of {names:?}"))
+            })?;
+            specialization.extend(overrides);
+        }
+        Ok(specialization)
+    }
+
+    /// Checks that `features`, the ones the device was created with, include those the fixed
+    /// function state needs.
+    pub fn check_features(&self, features: &Features) -> Result<()> {
+        let rasterization = &self.rasterization;
+        if rasterization.polygon_mode != PolygonMode::Fill && !features.fill_mode_non_solid {
+            let name = POLYGON_MODES
+                .iter()
+                .find(|(_, mode)| *mode == rasterization.polygon_mode)
+                .map_or("", |(name, _)| name);
+            Err(self.error(format!(
+                "rasterization.polygon_mode: `{name}` requires the fill_mode_non_solid device \
+                 feature, which is not enabled"
+            )))?
+        }
+        if rasterization.line_width != 1.0 && !features.wide_lines {
+            Err(self.error(format!(
+                "rasterization.line_width: {} requires the wide_lines device feature, which is \
+                 not enabled",
+                rasterization.line_width
+            )))?
+        }
+        Ok(())
+    }
+
+    pub fn error(&self, message: impl Display) -> anyhow::Error {
+        AppError::PipelineDescription {
+            file: self.source.clone(),
+            message: message.to_string(),
+        }
+        .into()
+    }
+
+    fn parse(text: &str, source: &str, base_dir: &Path) -> Result<Self> {
+        let document = parse_document(text, source, description_error)?;
+        let root = Section::root(source, &document, description_error);
+        root.check_keys(&[
+            "dynamic_states",
+            "shaders",
+            "vertex_input",
+            "input_assembly",
+            "rasterization",
+            "depth",
+            "color_blend",
+            "specialization",
+            "variants",
+            "defines",
+        ])?;
+
+        let mut description = Self {
+            source: source.to_string(),
+            path: None,
+            vertex_shader: None,
+            fragment_shader: None,
+            entry_point: None,
+            // The defaults shown in `pipelines/triangle.toml`
+            vertex_input: VertexInputState::new(),
+            input_assembly: InputAssemblyState::default(),
+            rasterization: RasterizationState {
+                cull_mode: CullMode::Back,
+                front_face: FrontFace::Clockwise,
+                ..RasterizationState::default()
+            },
+            blend: vec![BlendMode::Opaque],
+            dynamic_states: HashSet::from_iter([DynamicState::Viewport, DynamicState::Scissor]),
+            specialization: Specialization::new(),
+            variants: BTreeMap::new(),
+            defines: BTreeMap::new(),
+        };
+
+        if let Some(dynamic_states) = root.choices("dynamic_states", DYNAMIC_STATES)? {
+            description.dynamic_states = dynamic_states.into_iter().collect();
+        }
+
+        if let Some(shaders) = root.section("shaders")? {
+            shaders.check_keys(&["vertex", "fragment", "entry_point"])?;
+            let path = |key| -> Result<_> { Ok(shaders.str(key)?.map(|p| base_dir.join(p))) };
+            description.vertex_shader = path("vertex")?;
+            description.fragment_shader = path("fragment")?;
+            description.entry_point = shaders.str("entry_point")?.map(str::to_string);
+        }
+
+        if let Some(vertex_input) = root.section("vertex_input")? {
+            vertex_input.check_keys(&["bindings", "attributes"])?;
+            description.vertex_input = parse_vertex_input(&vertex_input)?;
+            if !description.vertex_input.bindings.is_empty() {
+                // The pipeline draws without binding vertex buffers
+                return Err(vertex_input.error(
+                    "bindings",
+                    "vertex buffers are not supported yet, generate vertices from the vertex index",
+                ));
+            }
+        }
+
+        if let Some(input_assembly) = root.section("input_assembly")? {
+            input_assembly.check_keys(&["topology", "primitive_restart"])?;
+            let state = &mut description.input_assembly;
+            if let Some(topology) = input_assembly.choice("topology", TOPOLOGIES)? {
+                state.topology = topology;
+            }
+            if let Some(primitive_restart) = input_assembly.bool("primitive_restart")? {
+                state.primitive_restart_enable = primitive_restart;
+            }
+        }
+
+        if let Some(rasterization) = root.section("rasterization")? {
+            rasterization.check_keys(&["cull_mode", "front_face", "polygon_mode", "line_width"])?;
+            let state = &mut description.rasterization;
+            if let Some(cull_mode) = rasterization.choice("cull_mode", CULL_MODES)? {
+                state.cull_mode = cull_mode;
+            }
+            if let Some(front_face) = rasterization.choice("front_face", FRONT_FACES)? {
+                state.front_face = front_face;
+            }
+            if let Some(polygon_mode) = rasterization.choice("polygon_mode", POLYGON_MODES)? {
+                state.polygon_mode = polygon_mode;
+            }
+            if let Some(line_width) = rasterization.f32("line_width")? {
+                state.line_width = line_width;
+            }
+        }
+
+        if root.get("depth").is_some() {
+            // Neither the render pass nor dynamic rendering has a depth attachment to test against
+            return Err(root.error(
+                "depth",
+                "depth testing is not supported yet, the render target has no depth attachment",
+            ));
+        }
+
+        if let Some(color_blend) = root.section("color_blend")? {
+            color_blend.check_keys(&["mode"])?;
+            if let Some(blend) = color_blend.choices("mode", BLEND_MODES)? {
+                if blend.is_empty() {
+                    return Err(color_blend.error("mode", "at least one blend mode is required"));
+                }
+                description.blend = blend;
+            }
+        }
+
+        if let Some(specialization) = root.section("specialization")? {
+            description.specialization = specialization.specialization()?;
+        }
+
+        if let Some(variants) = root.section("variants")? {
+            for (name, _) in variants.table.iter() {
+                let variant = variants.required(variants.section(name)?, name)?;
+                description
+                    .variants
+                    .insert(name.to_string(), variant.specialization()?);
+            }
+        }
+
+        if let Some(defines) = root.section("defines")? {
+            for (name, item) in defines.table.iter() {
+                let value = match item.as_value() {
+                    Some(Value::String(value)) => value.value().clone(),
+                    Some(Value::Integer(value)) => value.value().to_string(),
+                    Some(Value::Float(value)) => value.value().to_string(),
+                    Some(Value::Boolean(value)) => u8::from(*value.value()).to_string(),
+                    _ => return Err(defines.error(name, "expected a string, number or boolean")),
+                };
+                description.defines.insert(name.to_string(), value);
+            }
+        }
+
+        Ok(description)
+    }
+}
+
+fn parse_vertex_input(vertex_input: &Section) -> Result<VertexInputState> {
+    let mut bindings = HashMap::default();
+    for binding in vertex_input.sections("bindings")? {
+        binding.check_keys(&["binding", "stride", "input_rate"])?;
+        let index = binding.required(binding.u32("binding")?, "binding")?;
+        let description = VertexInputBindingDescription {
+            stride: binding.required(binding.u32("stride")?, "stride")?,
+            input_rate: binding
+                .choice("input_rate", INPUT_RATES)?
+                .unwrap_or(VertexInputRate::Vertex),
+        };
+        if bindings.insert(index, description).is_some() {
+            return Err(binding.error("binding", format!("binding {index} is defined twice")));
+        }
+    }
+
+    let mut attributes = HashMap::default();
+    for attribute in vertex_input.sections("attributes")? {
+        attribute.check_keys(&["location", "binding", "format", "offset"])?;
+        let location = attribute.required(attribute.u32("location")?, "location")?;
+        let binding = attribute.u32("binding")?.unwrap_or(0);
+        if !bindings.contains_key(&binding) {
+            return Err(attribute.error("binding", format!("binding {binding} is not defined")));
+        }
+        let description = VertexInputAttributeDescription {
+            binding,
+            format: attribute.required(attribute.choice("format", VERTEX_FORMATS)?, "format")?,
+            offset: attribute.u32("offset")?.unwrap_or(0),
+        };
+        if attributes.insert(location, description).is_some() {
+            return Err(attribute.error("location", format!("location {location} is used twice")));
+        }
+    }
+
+    Ok(VertexInputState::new()
+        .bindings(bindings)
+        .attributes(attributes))
+}
+
+fn description_error(file: String, message: String) -> AppError {
+    AppError::PipelineDescription { file, message }
+}
+
+impl<'a> Section<'a> {
+    fn required<T>(&self, value: Option<T>, key: &str) -> Result<T> {
+        value.ok_or_else(|| self.error(key, "missing required key"))
+    }
+
+    /// An array of tables, written either as `[[key]]` headers or as an array of inline tables.
+    fn sections(&self, key: &str) -> Result<Vec<Section<'a>>> {
+        let Some(item) = self.get(key) else {
+            return Ok(Vec::new());
+        };
+        let tables: Vec<&'a dyn TableLike> = if let Some(array) = item.as_array_of_tables() {
+            array.iter().map(|table| table as &dyn TableLike).collect()
+        } else if let Some(array) = item.as_array() {
+            array
+                .iter()
+                .map(|value| {
+                    value
+                        .as_inline_table()
+                        .map(|table| table as &dyn TableLike)
+                        .ok_or_else(|| self.error(key, "expected an array of tables"))
+                })
+                .collect::<Result<_>>()?
+        } else {
+            return Err(self.error(key, "expected an array of tables"));
+        };
+        Ok(tables
+            .into_iter()
+            .enumerate()
+            .map(|(i, table)| self.child(format!("{}[{i}]", self.key_path(key)), table))
+            .collect())
+    }
+
+    fn u32(&self, key: &str) -> Result<Option<u32>> {
+        self.get(key)
+            .map(|item| {
+                item.as_integer()
+                    .and_then(|value| u32::try_from(value).ok())
+                    .ok_or_else(|| self.error(key, "expected a non-negative 32-bit integer"))
+            })
+            .transpose()
+    }
+
+    fn f32(&self, key: &str) -> Result<Option<f32>> {
+        self.get(key)
+            .map(|item| {
+                item.as_float()
+                    .or_else(|| item.as_integer().map(|value| value as f64))
+                    .map(|value| value as f32)
+                    .ok_or_else(|| self.error(key, "expected a number"))
+            })
+            .transpose()
+    }
+
+    /// Specialization constant values keyed by their constant ID.
+    fn specialization(&self) -> Result<Specialization> {
+        self.table
+            .iter()
+            .map(|(key, item)| {
+                let id = key
+                    .parse::<u32>()
+                    .map_err(|_| self.error(key, "expected a specialization constant ID"))?;
+                let value = if let Some(value) = item.as_bool() {
+                    SpecializationValue::Bool(value)
+                } else if let Some(value) = item.as_integer() {
+                    SpecializationValue::Int(value)
+                } else if let Some(value) = item.as_float() {
+                    SpecializationValue::Float(value)
+                } else {
+                    return Err(self.error(key, "expected a boolean or a number"));
+                };
+                Ok((id, value))
+            })
+            .collect()
+    }
+
+    fn choice<T: Copy>(&self, key: &str, choices: &[(&str, T)]) -> Result<Option<T>> {
+        self.str(key)?
+            .map(|name| self.lookup(key, name, choices))
+            .transpose()
+    }
+
+    /// A single name or an array of names.
+    fn choices<T: Copy>(&self, key: &str, choices: &[(&str, T)]) -> Result<Option<Vec<T>>> {
+        let Some(item) = self.get(key) else {
+            return Ok(None);
+        };
+        if let Some(name) = item.as_str() {
+            return Ok(Some(vec![self.lookup(key, name, choices)?]));
+        }
+        let array = item
+            .as_array()
+            .ok_or_else(|| self.error(key, "expected a string or an array of strings"))?;
+        array
+            .iter()
+            .map(|value| {
+                let name = value
+                    .as_str()
+                    .ok_or_else(|| self.error(key, "expected an array of strings"))?;
+                self.lookup(key, name, choices)
+            })
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    fn lookup<T: Copy>(&self, key: &str, name: &str, choices: &[(&str, T)]) -> Result<T> {
+        choices
+            .iter()
+            .find(|(choice, _)| *choice == name)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| {
+                let names: Vec<_> = choices.iter().map(|(choice, _)| *choice).collect();
+                self.error(key, format!("unknown value `{name}`, expected one of {names:?}"))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<PipelineDescription> {
+        PipelineDescription::parse(text, "test.toml", Path::new("pipelines"))
+    }
+
+    fn parse_error(text: &str) -> String {
+        parse(text).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn defaults_match_the_builtin_description() {
+        let builtin =
+            PipelineDescription::parse(BUILTIN_DESCRIPTION, BUILTIN_SOURCE, Path::new("")).unwrap();
+        for description in [parse("").unwrap(), builtin] {
+            assert!(description.vertex_input.bindings.is_empty());
+            assert!(description.vertex_input.attributes.is_empty());
+            let input_assembly = &description.input_assembly;
+            assert_eq!(input_assembly.topology, PrimitiveTopology::TriangleList);
+            assert!(!input_assembly.primitive_restart_enable);
+            let rasterization = &description.rasterization;
+            assert_eq!(rasterization.cull_mode, CullMode::Back);
+            assert_eq!(rasterization.front_face, FrontFace::Clockwise);
+            assert_eq!(rasterization.polygon_mode, PolygonMode::Fill);
+            assert_eq!(rasterization.line_width, 1.0);
+            assert!(matches!(description.blend.as_slice(), [BlendMode::Opaque]));
+            assert_eq!(
+                description.dynamic_states,
+                HashSet::from_iter([DynamicState::Viewport, DynamicState::Scissor])
+            );
+            assert!(description.defines.is_empty());
+        }
+    }
+
+    #[test]
+    fn reads_shader_paths_relative_to_the_file() {
+        let description = parse("[shaders]\nvertex = \"a.vert\"\nentry_point = \"vs\"").unwrap();
+        assert_eq!(
+            description.vertex_shader,
+            Some(PathBuf::from("pipelines/a.vert"))
+        );
+        assert_eq!(description.fragment_shader, None);
+        assert_eq!(description.entry_point.as_deref(), Some("vs"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        for (text, path) in [
+            ("colour_blend = {}", "colour_blend"),
+            ("[shaders]\nvertx = \"a.vert\"", "shaders.vertx"),
+            (
+                "[rasterization]\nline_with = 2.0",
+                "rasterization.line_with",
+            ),
+        ] {
+            let error = parse_error(text);
+            assert!(
+                error.contains(&format!("{path}: unknown key")),
+                "{text}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_mistyped_values() {
+        for (text, message) in [
+            ("shaders = 1", "shaders: expected a table"),
+            ("[shaders]\nvertex = 1", "shaders.vertex: expected a string"),
+            (
+                "[input_assembly]\nprimitive_restart = \"yes\"",
+                "input_assembly.primitive_restart: expected a boolean",
+            ),
+            (
+                "[rasterization]\ncull_mode = \"sideways\"",
+                "rasterization.cull_mode: unknown value `sideways`",
+            ),
+            (
+                "[color_blend]\nmode = []",
+                "color_blend.mode: at least one blend mode",
+            ),
+            (
+                "[specialization]\nzero = 1",
+                "specialization.zero: expected a specialization",
+            ),
+            (
+                "[defines]\nLIGHTS = [4]",
+                "defines.LIGHTS: expected a string, number or boolean",
+            ),
+        ] {
+            let error = parse_error(text);
+            assert!(error.contains(message), "{text}: {error}");
+        }
+    }
+
+    #[test]
+    fn variants_override_the_shared_specialization() {
+        let description = parse(
+            "[specialization]\n0 = 1\n1 = true\n\n[variants.fast]\n1 = false\n2 = 0.5\n\n\
+             [variants.empty]",
+        )
+        .unwrap();
+        let shared = Specialization::from([
+            (0, SpecializationValue::Int(1)),
+            (1, SpecializationValue::Bool(true)),
+        ]);
+        assert_eq!(description.variant_specialization(None).unwrap(), shared);
+        assert_eq!(
+            description.variant_specialization(Some("empty")).unwrap(),
+            shared
+        );
+        assert_eq!(
+            description.variant_specialization(Some("fast")).unwrap(),
+            Specialization::from([
+                (0, SpecializationValue::Int(1)),
+                (1, SpecializationValue::Bool(false)),
+                (2, SpecializationValue::Float(0.5)),
+            ])
+        );
+        let error = description
+            .variant_specialization(Some("slow"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown variant `slow`"), "{error}");
+    }
+
+    #[test]
+    fn rejects_unsupported_dynamic_states() {
+        let description = parse("dynamic_states = \"viewport\"").unwrap();
+        assert_eq!(
+            description.dynamic_states,
+            HashSet::from_iter([DynamicState::Viewport])
+        );
+        for state in ["line_width", "cull_mode", "depth_test_enable"] {
+            let error = parse_error(&format!("dynamic_states = [\"viewport\", \"{state}\"]"));
+            assert!(
+                error.contains(&format!("dynamic_states: unknown value `{state}`")),
+                "{state}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_pipeline_state() {
+        let error = parse_error("[depth]\ntest = true");
+        assert!(
+            error.contains("depth: depth testing is not supported"),
+            "{error}"
+        );
+        let error = parse_error(
+            "[[vertex_input.bindings]]\nbinding = 0\nstride = 8\n\n\
+             [[vertex_input.attributes]]\nlocation = 0\nformat = \"r32g32_sfloat\"",
+        );
+        assert!(
+            error.contains("vertex_input.bindings: vertex buffers are not supported"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn checks_the_features_of_the_fixed_function_state() {
+        let description = parse("[rasterization]\npolygon_mode = \"line\"").unwrap();
+        assert!(description.check_features(&Features::empty()).is_err());
+        let features = Features {
+            fill_mode_non_solid: true,
+            ..Features::empty()
+        };
+        assert!(description.check_features(&features).is_ok());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::config::AppConfig;
+use crate::vulkan::debug::set_object_name;
+use crate::vulkan::graphics_pipeline::create_graphics_pipeline;
+use crate::vulkan::pipeline_description::PipelineDescription;
+use crate::vulkan::shader::GraphicsShaders;
+use crate::vulkan::shader_include::canonical;
+use crate::vulkan::specialization::Specialization;
+use crate::vulkan::surface_format::{OutputTransfer, OUTPUT_TRANSFER_CONSTANT_ID};
+use ahash::HashMap;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{debug, info};
+use vulkano::device::Device;
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::subpass::PipelineSubpassType;
+use vulkano::pipeline::{GraphicsPipeline, PipelineLayout};
+use vulkano::swapchain::Swapchain;
+
+/// Graphics pipelines built from one description and its shaders, keyed by the values of the
+/// specialization constants, so variants with the same values share a pipeline.
+pub struct PipelineVariants {
+    pipelines: HashMap<Specialization, (Arc<PipelineLayout>, Arc<GraphicsPipeline>)>,
+    shaders: GraphicsShaders,
+    description: PipelineDescription,
+    pipeline_cache: Arc<PipelineCache>,
+    subpass: PipelineSubpassType,
+    swapchain: Arc<Swapchain>,
+    device: Arc<Device>,
+}
+
+impl PipelineVariants {
+    pub fn new(
+        device: &Arc<Device>,
+        swapchain: &Arc<Swapchain>,
+        subpass: &PipelineSubpassType,
+        pipeline_cache: &Arc<PipelineCache>,
+        shaders: GraphicsShaders,
+        description: PipelineDescription,
+    ) -> Self {
+        Self {
+            pipelines: HashMap::default(),
+            shaders,
+            description,
+            pipeline_cache: pipeline_cache.clone(),
+            subpass: subpass.clone(),
+            swapchain: swapchain.clone(),
+            device: device.clone(),
+        }
+    }
+
+    /// Loads the pipeline description and shaders given in `config` and builds every variant.
+    pub fn load(
+        device: &Arc<Device>,
+        swapchain: &Arc<Swapchain>,
+        subpass: &PipelineSubpassType,
+        pipeline_cache: &Arc<PipelineCache>,
+        config: &AppConfig,
+    ) -> Result<Self> {
+        let description = PipelineDescription::load(config)?;
+        let shaders = GraphicsShaders::load(device, config, &description)?;
+        let mut pipeline_variants = Self::new(
+            device,
+            swapchain,
+            subpass,
+            pipeline_cache,
+            shaders,
+            description,
+        );
+        pipeline_variants.build_all()?;
+        Ok(pipeline_variants)
+    }
+
+    /// Drops the pipelines built for the previous swapchain, they are rebuilt on first use.
+    pub fn set_swapchain(&mut self, swapchain: &Arc<Swapchain>) {
+        self.pipelines.clear();
+        self.swapchain = swapchain.clone();
+    }
+
+    /// Files the pipelines are built from: the description, shader sources and their includes.
+    pub fn dependencies(&self) -> BTreeSet<PathBuf> {
+        let mut dependencies = self.shaders.sources.clone();
+        dependencies.extend(self.description.path.as_deref().map(canonical));
+        dependencies
+    }
+
+    /// Returns the pipeline of the named variant, or of the shared specialization constants
+    /// without a name, building it on first use.
+    pub fn get(
+        &mut self,
+        variant: Option<&str>,
+    ) -> Result<(Arc<PipelineLayout>, Arc<GraphicsPipeline>)> {
+        let mut specialization = self.description.variant_specialization(variant)?;
+        if specialization.contains_key(&OUTPUT_TRANSFER_CONSTANT_ID) {
+            Err(self.description.error(format!(
+                "specialization constant {OUTPUT_TRANSFER_CONSTANT_ID} is reserved for the \
+                 output transfer function"
+            )))?
+        }
+        specialization.extend(OutputTransfer::of(&self.swapchain).specialization());
+        if let Some(pipeline) = self.pipelines.get(&specialization) {
+            return Ok(pipeline.clone());
+        }
+
+        let name = match variant {
+            Some(variant) => format!("graphics pipeline ({variant})"),
+            None => "graphics pipeline".to_string(),
+        };
+        debug!("building {name} with specialization constants {specialization:?}");
+        let (pipeline_layout, graphics_pipeline) = create_graphics_pipeline(
+            &self.device,
+            &self.swapchain,
+            &self.subpass,
+            &self.shaders,
+            &self.description,
+            &specialization,
+            &self.pipeline_cache,
+        )
+        .with_context(|| format!("can not build {name}"))?;
+        set_object_name(&pipeline_layout, &format!("{name} layout"))?;
+        set_object_name(&graphics_pipeline, &name)?;
+
+        let pipeline = (pipeline_layout, graphics_pipeline);
+        self.pipelines.insert(specialization, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Builds the pipelines of every variant ahead of their first use.
+    pub fn build_all(&mut self) -> Result<()> {
+        let names: Vec<_> = self.description.variants.keys().cloned().collect();
+        self.get(None)?;
+        for name in &names {
+            self.get(Some(name))?;
+        }
+        info!(
+            "{} pipeline variants built as {} pipelines",
+            names.len() + 1,
+            self.pipelines.len()
+        );
+        Ok(())
+    }
+}
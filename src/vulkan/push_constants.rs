@@ -0,0 +1,63 @@
+use anyhow::{bail, Result};
+use std::mem::size_of;
+use std::sync::Arc;
+use vulkano::buffer::BufferContents;
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::pipeline::PipelineLayout;
+
+/// Per-draw data, laid out like the `ObjectPushConstants` block of `shaders/shader.vert`.
+#[derive(BufferContents, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct ObjectPushConstants {
+    /// Column-major matrix applied to the vertex positions, followed by the view-projection
+    /// matrix of the camera when one is set
+    pub transform: [[f32; 4]; 4],
+    /// Multiplied with the vertex colors
+    pub tint: [f32; 4],
+    pub material_index: u32,
+}
+
+impl Default for ObjectPushConstants {
+    fn default() -> Self {
+        Self {
+            transform: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            tint: [1.0; 4],
+            material_index: 0,
+        }
+    }
+}
+
+/// Pushes `push_constants` at the start of the push constant ranges of `pipeline_layout`.
+///
+/// Does nothing if the shaders of the pipeline declare no push constants, so shaders loaded at
+/// runtime do not have to use them; that is logged once, when the pipeline is built.
+pub fn push_constants<Pc: BufferContents, L, A: CommandBufferAllocator>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    pipeline_layout: &Arc<PipelineLayout>,
+    push_constants: Pc,
+) -> Result<()> {
+    let ranges = pipeline_layout.push_constant_ranges();
+    if ranges.is_empty() {
+        return Ok(());
+    }
+    let size = size_of::<Pc>() as u32;
+    let layout_size = ranges
+        .iter()
+        .map(|range| range.offset + range.size)
+        .max()
+        .unwrap_or_default();
+    if size != layout_size {
+        bail!(
+            "{} is {size} bytes, but the push constants of the shaders are {layout_size} bytes",
+            std::any::type_name::<Pc>()
+        );
+    }
+    builder.push_constants(pipeline_layout.clone(), 0, push_constants)?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use crate::app_error::{DeviceRejection, QueueFamilyType};
 use tracing::info;
 use vulkano::device::physical::PhysicalDevice;
 use vulkano::device::QueueFlags;
@@ -8,30 +8,38 @@ use vulkano::swapchain::Surface;
 pub struct QueueFamilyIndices {
     pub graphics_family: u32,
     pub present_family: u32,
+    /// A family without graphics support if there is one, so compute work runs asynchronously
+    pub compute_family: Option<u32>,
 }
 
 impl QueueFamilyIndices {
     pub fn find(
         physical_device: &PhysicalDevice,
         surface: &Surface,
-    ) -> Result<Option<QueueFamilyIndices>> {
+    ) -> Result<QueueFamilyIndices, Vec<DeviceRejection>> {
         let mut queue_family_indices = QueueFamilyIndicesBuilder::default();
         let mut last_err = None;
         for (i, prop) in physical_device.queue_family_properties().iter().enumerate() {
             let i = i as u32;
-            let mut changed = false;
 
             if queue_family_indices.graphics_family.is_none()
                 && prop.queue_flags.contains(QueueFlags::GRAPHICS)
             {
-                changed |= true;
                 queue_family_indices.graphics_family = Some(i);
             }
 
+            if prop.queue_flags.contains(QueueFlags::COMPUTE) {
+                let dedicated = !prop.queue_flags.contains(QueueFlags::GRAPHICS);
+                if dedicated && queue_family_indices.dedicated_compute_family.is_none() {
+                    queue_family_indices.dedicated_compute_family = Some(i);
+                } else if !dedicated && queue_family_indices.shared_compute_family.is_none() {
+                    queue_family_indices.shared_compute_family = Some(i);
+                }
+            }
+
             if queue_family_indices.present_family.is_none() {
                 match physical_device.surface_support(i, surface) {
                     Ok(true) => {
-                        changed |= true;
                         queue_family_indices.present_family = Some(i);
                     }
                     Ok(false) => (),
@@ -45,19 +53,18 @@ impl QueueFamilyIndices {
                     }
                 }
             }
-
-            if changed {
-                if let queue_family_indices @ Some(_) = queue_family_indices.build() {
-                    return Ok(queue_family_indices);
-                }
-            }
         }
 
+        if let Some(queue_family_indices) = queue_family_indices.build() {
+            return Ok(queue_family_indices);
+        }
+        let mut rejections = queue_family_indices.missing();
         if let Some(err) = last_err {
-            Err(err.into())
-        } else {
-            Ok(None)
+            rejections.push(DeviceRejection::QueryFailed(format!(
+                "surface support query ends with error {err}"
+            )));
         }
+        Err(rejections)
     }
 }
 
@@ -65,6 +72,8 @@ impl QueueFamilyIndices {
 struct QueueFamilyIndicesBuilder {
     graphics_family: Option<u32>,
     present_family: Option<u32>,
+    dedicated_compute_family: Option<u32>,
+    shared_compute_family: Option<u32>,
 }
 
 impl QueueFamilyIndicesBuilder {
@@ -73,6 +82,18 @@ impl QueueFamilyIndicesBuilder {
         Some(QueueFamilyIndices {
             graphics_family: self.graphics_family?,
             present_family: self.present_family?,
+            compute_family: self.dedicated_compute_family.or(self.shared_compute_family),
         })
     }
+
+    fn missing(&self) -> Vec<DeviceRejection> {
+        [
+            (self.graphics_family, QueueFamilyType::Graphics),
+            (self.present_family, QueueFamilyType::Present),
+        ]
+        .into_iter()
+        .filter(|(family, _)| family.is_none())
+        .map(|(_, queue_type)| DeviceRejection::MissingQueueFamily(queue_type))
+        .collect()
+    }
 }
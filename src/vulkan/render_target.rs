@@ -0,0 +1,158 @@
+use crate::app_error::AppError;
+use crate::vulkan::debug::{set_object_name, set_object_names};
+use crate::vulkan::framebuffers::create_framebuffers;
+use crate::vulkan::render_pass::create_render_pass;
+use anyhow::Result;
+use std::sync::Arc;
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, RenderPassBeginInfo, RenderingAttachmentInfo, RenderingInfo,
+    SubpassBeginInfo, SubpassContents, SubpassEndInfo,
+};
+use vulkano::device::Device;
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::graphics::subpass::{PipelineRenderingCreateInfo, PipelineSubpassType};
+use vulkano::render_pass::{AttachmentLoadOp, AttachmentStoreOp, Framebuffer, RenderPass, Subpass};
+use vulkano::swapchain::Swapchain;
+
+/// Attachments the graphics pipelines draw to, one set per swapchain image.
+pub enum RenderTarget {
+    /// A render pass with a framebuffer per swapchain image
+    RenderPass {
+        render_pass: Arc<RenderPass>,
+        framebuffers: Vec<Arc<Framebuffer>>,
+    },
+    /// Dynamic rendering straight to the swapchain image views
+    DynamicRendering {
+        image_views: Vec<Arc<ImageView>>,
+        format: Format,
+    },
+}
+
+impl RenderTarget {
+    pub fn new(
+        device: &Arc<Device>,
+        swapchain: &Arc<Swapchain>,
+        image_views: &[Arc<ImageView>],
+        dynamic_rendering: bool,
+    ) -> Result<Self> {
+        if dynamic_rendering {
+            return Ok(Self::DynamicRendering {
+                image_views: image_views.to_vec(),
+                format: swapchain.image_format(),
+            });
+        }
+        let render_pass = create_render_pass(device, swapchain)?;
+        let framebuffers = create_framebuffers(&render_pass, image_views)?;
+        Ok(Self::RenderPass {
+            render_pass,
+            framebuffers,
+        })
+    }
+
+    /// Switches to the image views of a recreated swapchain with the same format.
+    pub fn resize(&mut self, image_views: &[Arc<ImageView>]) -> Result<()> {
+        match self {
+            Self::RenderPass {
+                render_pass,
+                framebuffers,
+            } => *framebuffers = create_framebuffers(render_pass, image_views)?,
+            Self::DynamicRendering {
+                image_views: views, ..
+            } => *views = image_views.to_vec(),
+        }
+        Ok(())
+    }
+
+    /// What graphics pipelines drawing to the target are created for.
+    pub fn pipeline_subpass(&self) -> Result<PipelineSubpassType> {
+        Ok(match self {
+            Self::RenderPass { render_pass, .. } => {
+                let subpass_id = 0;
+                Subpass::from(render_pass.clone(), subpass_id)
+                    .ok_or(AppError::SubpassNotFound(subpass_id))?
+                    .into()
+            }
+            Self::DynamicRendering { format, .. } => PipelineRenderingCreateInfo {
+                color_attachment_formats: vec![Some(*format)],
+                ..PipelineRenderingCreateInfo::default()
+            }
+            .into(),
+        })
+    }
+
+    /// Begins drawing to the attachments of a swapchain image, cleared to `clear_color`.
+    ///
+    /// Returns the extent of the attachments.
+    pub fn begin<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        image_index: u32,
+        clear_color: ClearValue,
+    ) -> Result<[u32; 2]> {
+        match self {
+            Self::RenderPass { framebuffers, .. } => {
+                let framebuffer = &framebuffers[image_index as usize];
+                builder.begin_render_pass(
+                    RenderPassBeginInfo {
+                        clear_values: vec![Some(clear_color)],
+                        ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+                    },
+                    SubpassBeginInfo {
+                        contents: SubpassContents::Inline,
+                        ..SubpassBeginInfo::default()
+                    },
+                )?;
+                Ok(framebuffer.extent())
+            }
+            Self::DynamicRendering { image_views, .. } => {
+                let image_view = &image_views[image_index as usize];
+                builder.begin_rendering(RenderingInfo {
+                    color_attachments: vec![Some(RenderingAttachmentInfo {
+                        load_op: AttachmentLoadOp::Clear,
+                        store_op: AttachmentStoreOp::Store,
+                        clear_value: Some(clear_color),
+                        ..RenderingAttachmentInfo::image_view(image_view.clone())
+                    })],
+                    ..RenderingInfo::default()
+                })?;
+                let [width, height, _] = image_view.image().extent();
+                Ok([width, height])
+            }
+        }
+    }
+
+    pub fn end<L, A: CommandBufferAllocator>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+    ) -> Result<()> {
+        match self {
+            Self::RenderPass { .. } => builder.end_render_pass(SubpassEndInfo::default())?,
+            Self::DynamicRendering { .. } => builder.end_rendering()?,
+        };
+        Ok(())
+    }
+
+    pub fn set_object_names(&self) -> Result<()> {
+        if let Self::RenderPass {
+            render_pass,
+            framebuffers,
+        } = self
+        {
+            set_object_name(render_pass, "render pass")?;
+            set_object_names(framebuffers, "framebuffer")?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of color attachments pipelines created for `subpass` write to.
+pub fn color_attachment_count(subpass: &PipelineSubpassType) -> u32 {
+    match subpass {
+        PipelineSubpassType::BeginRenderPass(subpass) => subpass.num_color_attachments(),
+        PipelineSubpassType::BeginRendering(rendering_info) => {
+            rendering_info.color_attachment_formats.len() as u32
+        }
+    }
+}
@@ -1,6 +1,19 @@
+use crate::config::AppConfig;
+use crate::vulkan::pipeline_description::PipelineDescription;
+use crate::vulkan::shader_loader::{entry_point, load_shader_file, CompileSettings};
+use crate::vulkan::specialization::{specialize, Specialization};
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use vulkano::device::Device;
+use vulkano::shader::{EntryPoint, ShaderModule, ShaderStage};
+use vulkano::{Validated, VulkanError};
+
 vulkano_shaders::shader! {
     vulkan_version: "1.2",
     spirv_version: "1.6",
+    include: ["shaders/include"],
     shaders: {
         vertex: {
             ty: "vertex",
@@ -12,3 +25,115 @@ vulkano_shaders::shader! {
         }
     }
 }
+
+const DEFAULT_ENTRY_POINT: &str = "main";
+
+/// Directory with the sources of the embedded shaders.
+const SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders");
+
+/// Shader root searched by `#include`, unless another one is given in the config.
+const SHADER_INCLUDE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/include");
+
+/// Directories searched by `#include` in shaders compiled at runtime.
+pub fn include_dirs(config: &AppConfig) -> Vec<PathBuf> {
+    vec![config
+        .shader_root
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(SHADER_INCLUDE_DIR))]
+}
+
+/// Loader of an embedded shader module, generated by `shader!`.
+type LoadEmbedded = fn(Arc<Device>) -> Result<Arc<ShaderModule>, Validated<VulkanError>>;
+
+pub struct GraphicsShaders {
+    pub vertex: EntryPoint,
+    pub fragment: EntryPoint,
+    /// Files of the shaders compiled at runtime, with every file they include
+    pub sources: BTreeSet<PathBuf>,
+}
+
+impl GraphicsShaders {
+    /// Loads shader files given in `config` or in the pipeline description, falling back to the
+    /// shaders embedded at build time.
+    ///
+    /// With hot reload enabled or defines in the description, sources of the embedded shaders are
+    /// compiled at runtime instead.
+    pub fn load(
+        device: &Arc<Device>,
+        config: &AppConfig,
+        description: &PipelineDescription,
+    ) -> Result<Self> {
+        let entry_point_name = config
+            .shader_entry_point
+            .as_deref()
+            .or(description.entry_point.as_deref())
+            .unwrap_or(DEFAULT_ENTRY_POINT);
+        let settings = CompileSettings {
+            include_dirs: include_dirs(config),
+            defines: description.defines.clone(),
+        };
+        let (vertex_path, fragment_path) = Self::paths(config, description);
+
+        let mut sources = BTreeSet::new();
+        let mut load_stage = |path: Option<PathBuf>, stage, load_embedded: LoadEmbedded| {
+            match path {
+                Some(path) => {
+                    let shader =
+                        load_shader_file(device, &path, stage, entry_point_name, &settings)?;
+                    sources.extend(shader.sources);
+                    entry_point(
+                        &shader.module,
+                        &path.display().to_string(),
+                        entry_point_name,
+                        stage,
+                    )
+                }
+                None => entry_point(
+                    &load_embedded(device.clone())?,
+                    &format!("embedded {stage:?} shader"),
+                    DEFAULT_ENTRY_POINT,
+                    stage,
+                ),
+            }
+        };
+        let vertex = load_stage(vertex_path, ShaderStage::Vertex, load_vertex)?;
+        let fragment = load_stage(fragment_path, ShaderStage::Fragment, load_fragment)?;
+
+        Ok(Self {
+            vertex,
+            fragment,
+            sources,
+        })
+    }
+
+    /// Returns the shaders with the specialization constants applied.
+    pub fn specialize(&self, specialization: &Specialization) -> Result<Self> {
+        Ok(Self {
+            vertex: specialize(&self.vertex, specialization)?,
+            fragment: specialize(&self.fragment, specialization)?,
+            sources: self.sources.clone(),
+        })
+    }
+
+    fn paths(
+        config: &AppConfig,
+        description: &PipelineDescription,
+    ) -> (Option<PathBuf>, Option<PathBuf>) {
+        let compile_embedded = config.hot_reload || !description.defines.is_empty();
+        let embedded_source = |file_name| {
+            compile_embedded.then(|| Path::new(SHADER_DIR).join(file_name))
+        };
+        (
+            config
+                .vertex_shader
+                .clone()
+                .or_else(|| description.vertex_shader.clone())
+                .or_else(|| embedded_source("shader.vert")),
+            config
+                .fragment_shader
+                .clone()
+                .or_else(|| description.fragment_shader.clone())
+                .or_else(|| embedded_source("shader.frag")),
+        )
+    }
+}
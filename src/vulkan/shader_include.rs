@@ -0,0 +1,170 @@
+use shaderc::{IncludeCallbackResult, IncludeType, ResolvedInclude};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files included while compiling a shader, as edges from the including to the included file.
+///
+/// Paths are canonical, so a file reached through different relative paths is one node.
+#[derive(Debug, Default)]
+pub struct IncludeGraph {
+    edges: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl IncludeGraph {
+    /// Every file included directly or indirectly.
+    pub fn files(&self) -> BTreeSet<PathBuf> {
+        self.edges.values().flatten().cloned().collect()
+    }
+
+    /// Adds the edge `from` -> `to`, or returns the include chain it would close into a cycle.
+    fn add(&mut self, from: &Path, to: &Path) -> Result<(), Vec<PathBuf>> {
+        if let Some(mut chain) = self.path(to, from) {
+            chain.push(to.to_path_buf());
+            return Err(chain);
+        }
+        self.edges
+            .entry(from.to_path_buf())
+            .or_default()
+            .insert(to.to_path_buf());
+        Ok(())
+    }
+
+    /// A chain of includes leading from `from` to `to`, both included.
+    fn path(&self, from: &Path, to: &Path) -> Option<Vec<PathBuf>> {
+        if from == to {
+            return Some(vec![from.to_path_buf()]);
+        }
+        self.edges.get(from)?.iter().find_map(|next| {
+            let mut chain = self.path(next, to)?;
+            chain.insert(0, from.to_path_buf());
+            Some(chain)
+        })
+    }
+}
+
+/// Resolves an `#include` for the shaderc include callback and records it in `graph`.
+///
+/// `#include "file"` is looked up next to the including file first, then in `include_dirs`;
+/// `#include <file>` only in `include_dirs`.
+pub fn resolve_include(
+    requested: &str,
+    include_type: IncludeType,
+    requesting: &str,
+    include_dirs: &[PathBuf],
+    graph: &mut IncludeGraph,
+) -> IncludeCallbackResult {
+    let requesting = canonical(Path::new(requesting));
+    let relative_dir = match include_type {
+        IncludeType::Relative => requesting.parent(),
+        IncludeType::Standard => None,
+    };
+    let resolved = relative_dir
+        .into_iter()
+        .chain(include_dirs.iter().map(PathBuf::as_path))
+        .map(|dir| dir.join(requested))
+        .find(|path| path.is_file())
+        .map(|path| canonical(&path))
+        .ok_or_else(|| {
+            format!("can not find `{requested}` next to the shader or in {include_dirs:?}")
+        })?;
+
+    graph.add(&requesting, &resolved).map_err(|chain| {
+        let chain: Vec<_> = chain.iter().map(|path| path.display().to_string()).collect();
+        format!("include cycle: {}", chain.join(" -> "))
+    })?;
+
+    let content = fs::read_to_string(&resolved)
+        .map_err(|e| format!("can not read {}: {e}", resolved.display()))?;
+    Ok(ResolvedInclude {
+        resolved_name: resolved.display().to_string(),
+        content,
+    })
+}
+
+/// Canonical form of `path`, or `path` itself if it can not be resolved, e.g. after removal.
+pub fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(format!("/shaders/{name}"))
+    }
+
+    fn add(graph: &mut IncludeGraph, from: &str, to: &str) -> Result<(), Vec<PathBuf>> {
+        graph.add(&path(from), &path(to))
+    }
+
+    #[test]
+    fn records_included_files() {
+        let mut graph = IncludeGraph::default();
+        add(&mut graph, "main.frag", "a.glsl").unwrap();
+        add(&mut graph, "main.frag", "b.glsl").unwrap();
+        add(&mut graph, "a.glsl", "common.glsl").unwrap();
+        // Including a file from two places is not a cycle
+        add(&mut graph, "b.glsl", "common.glsl").unwrap();
+        assert_eq!(
+            graph.files(),
+            BTreeSet::from([path("a.glsl"), path("b.glsl"), path("common.glsl")])
+        );
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let mut graph = IncludeGraph::default();
+        add(&mut graph, "main.frag", "a.glsl").unwrap();
+        add(&mut graph, "a.glsl", "b.glsl").unwrap();
+        assert_eq!(
+            add(&mut graph, "b.glsl", "a.glsl"),
+            Err(vec![path("a.glsl"), path("b.glsl"), path("a.glsl")])
+        );
+        assert_eq!(
+            add(&mut graph, "b.glsl", "main.frag"),
+            Err(vec![
+                path("main.frag"),
+                path("a.glsl"),
+                path("b.glsl"),
+                path("main.frag")
+            ])
+        );
+        assert_eq!(
+            add(&mut graph, "a.glsl", "a.glsl"),
+            Err(vec![path("a.glsl"), path("a.glsl")])
+        );
+        // Rejected edges are not recorded
+        assert_eq!(
+            graph.files(),
+            BTreeSet::from([path("a.glsl"), path("b.glsl")])
+        );
+    }
+
+    #[test]
+    fn resolve_include_reports_cycles_and_missing_files() {
+        let dir = env::temp_dir().join(format!("vulkt-include-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let shader = dir.join("main.frag");
+        fs::write(&shader, "#include \"a.glsl\"\n").unwrap();
+        fs::write(dir.join("a.glsl"), "#include \"main.frag\"\n").unwrap();
+        let requesting = shader.display().to_string();
+        let a = canonical(&dir.join("a.glsl")).display().to_string();
+
+        let mut graph = IncludeGraph::default();
+        let include = |graph: &mut IncludeGraph, requested, requesting: &str| {
+            resolve_include(requested, IncludeType::Relative, requesting, &[], graph)
+        };
+        let resolved = include(&mut graph, "a.glsl", &requesting).unwrap();
+        assert_eq!(resolved.resolved_name, a);
+        let error = include(&mut graph, "main.frag", &a).unwrap_err();
+        assert!(error.starts_with("include cycle: "), "{error}");
+        let error = include(&mut graph, "missing.glsl", &requesting).unwrap_err();
+        assert!(error.contains("can not find `missing.glsl`"), "{error}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+use crate::app_error::AppError;
+use crate::vulkan::shader_include::{canonical, resolve_include, IncludeGraph};
+use anyhow::{anyhow, Context, Result};
+use shaderc::{
+    CompileOptions, Compiler, EnvVersion, ShaderKind, SourceLanguage, SpirvVersion, TargetEnv,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+use vulkano::device::Device;
+use vulkano::shader::spirv::bytes_to_words;
+use vulkano::shader::{EntryPoint, ShaderModule, ShaderModuleCreateInfo, ShaderStage};
+
+/// Include directories and macro definitions for shader sources compiled at runtime.
+#[derive(Debug, Clone, Default)]
+pub struct CompileSettings {
+    pub include_dirs: Vec<PathBuf>,
+    pub defines: BTreeMap<String, String>,
+}
+
+pub struct LoadedShader {
+    pub module: Arc<ShaderModule>,
+    /// The shader file and every file it includes
+    pub sources: BTreeSet<PathBuf>,
+}
+
+/// Looks up `name` in `module` and checks that it is an entry point for `stage`.
+pub fn entry_point(
+    module: &Arc<ShaderModule>,
+    shader: &str,
+    name: &str,
+    stage: ShaderStage,
+) -> Result<EntryPoint> {
+    let entry_point = module
+        .entry_point(name)
+        .ok_or_else(|| AppError::EntryPointNotFound {
+            shader: shader.to_string(),
+            name: name.to_string(),
+        })?;
+    let found = ShaderStage::from(entry_point.info().execution_model);
+    if found != stage {
+        Err(AppError::ShaderStageMismatch {
+            shader: shader.to_string(),
+            name: name.to_string(),
+            expected: stage,
+            found,
+        })?
+    }
+    Ok(entry_point)
+}
+
+/// Loads a shader module from a SPIR-V (`.spv`), HLSL (`.hlsl`) or GLSL (anything else) file.
+///
+/// SPIR-V files are trusted: they reach the driver without being validated.
+pub fn load_shader_file(
+    device: &Arc<Device>,
+    path: &Path,
+    stage: ShaderStage,
+    entry_point_name: &str,
+    settings: &CompileSettings,
+) -> Result<LoadedShader> {
+    info!("loading {stage:?} shader from {}", path.display());
+    let mut sources = BTreeSet::from([canonical(path)]);
+    let words = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("spv") => {
+            if !settings.defines.is_empty() {
+                warn!("defines are ignored for SPIR-V shader {}", path.display());
+            }
+            let bytes =
+                fs::read(path).with_context(|| format!("can not read {}", path.display()))?;
+            bytes_to_words(&bytes)
+                .with_context(|| format!("{} is not a SPIR-V binary", path.display()))?
+                .into_owned()
+        }
+        extension => {
+            let source_language = match extension {
+                Some("hlsl") => SourceLanguage::HLSL,
+                _ => SourceLanguage::GLSL,
+            };
+            let (words, includes) =
+                compile_shader_file(path, stage, entry_point_name, source_language, settings)?;
+            sources.extend(includes);
+            words
+        }
+    };
+    // SAFETY: vulkano only reflects the code, it does not validate it. Sources are compiled by
+    // shaderc, which emits valid SPIR-V; `.spv` files are trusted to be valid for the device
+    let module = unsafe { ShaderModule::new(device.clone(), ShaderModuleCreateInfo::new(&words)) }?;
+    Ok(LoadedShader { module, sources })
+}
+
+/// Compiles a shader source, returning the SPIR-V words and the files it includes.
+fn compile_shader_file(
+    path: &Path,
+    stage: ShaderStage,
+    entry_point_name: &str,
+    source_language: SourceLanguage,
+    settings: &CompileSettings,
+) -> Result<(Vec<u32>, BTreeSet<PathBuf>)> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("can not read {}", path.display()))?;
+    let shader_kind = shader_kind(stage)?;
+
+    // Declared before `options`, whose include callback borrows it
+    let include_graph = RefCell::new(IncludeGraph::default());
+    let compiler = Compiler::new().ok_or_else(|| anyhow!("can not create shader compiler"))?;
+    let mut options =
+        CompileOptions::new().ok_or_else(|| anyhow!("can not create shader compile options"))?;
+    options.set_target_env(TargetEnv::Vulkan, EnvVersion::Vulkan1_2 as u32);
+    options.set_target_spirv(SpirvVersion::V1_6);
+    options.set_source_language(source_language);
+    for (name, value) in &settings.defines {
+        options.add_macro_definition(name, Some(value));
+    }
+    options.set_include_callback(|requested, include_type, requesting, _depth| {
+        resolve_include(
+            requested,
+            include_type,
+            requesting,
+            &settings.include_dirs,
+            &mut include_graph.borrow_mut(),
+        )
+    });
+
+    let file_name = path.display().to_string();
+    let artifact = compiler
+        .compile_into_spirv(
+            &source,
+            shader_kind,
+            &file_name,
+            entry_point_name,
+            Some(&options),
+        )
+        .map_err(|e| match e {
+            shaderc::Error::CompilationError(_, diagnostics) => {
+                anyhow!(AppError::ShaderCompilation {
+                    shader: file_name.clone(),
+                    diagnostics,
+                })
+            }
+            e => anyhow!(e).context(format!("can not compile {file_name}")),
+        })?;
+    if artifact.get_num_warnings() > 0 {
+        warn!(
+            "{file_name} compiled with warnings:\n{}",
+            artifact.get_warning_messages()
+        );
+    }
+    let includes = include_graph.borrow().files();
+    Ok((artifact.as_binary().to_vec(), includes))
+}
+
+fn shader_kind(stage: ShaderStage) -> Result<ShaderKind> {
+    Ok(match stage {
+        ShaderStage::Vertex => ShaderKind::Vertex,
+        ShaderStage::TessellationControl => ShaderKind::TessControl,
+        ShaderStage::TessellationEvaluation => ShaderKind::TessEvaluation,
+        ShaderStage::Geometry => ShaderKind::Geometry,
+        ShaderStage::Fragment => ShaderKind::Fragment,
+        ShaderStage::Compute => ShaderKind::Compute,
+        stage => Err(AppError::UnsupportedShaderStage(stage))?,
+    })
+}
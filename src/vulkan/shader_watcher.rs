@@ -0,0 +1,74 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Detects modified shader files by polling their modification times.
+///
+/// Only the given files are checked, so a file replaced by an editor saving atomically counts as
+/// modified, and a removed file as changed once it is back.
+pub struct ShaderWatcher {
+    files: BTreeSet<PathBuf>,
+    modified: HashMap<PathBuf, SystemTime>,
+    last_poll: Instant,
+}
+
+impl ShaderWatcher {
+    pub fn new(files: BTreeSet<PathBuf>) -> Self {
+        info!("watching shader files {files:?}");
+        let mut watcher = Self {
+            files,
+            modified: HashMap::new(),
+            last_poll: Instant::now(),
+        };
+        watcher.modified = watcher.scan();
+        watcher
+    }
+
+    /// Watches `files` from now on.
+    pub fn watch_files(&mut self, files: &BTreeSet<PathBuf>) {
+        if *files != self.files {
+            info!("watching shader files {files:?}");
+            self.files = files.clone();
+            self.modified = self.scan();
+        }
+    }
+
+    /// Returns the files changed or removed since the last call, at most once per
+    /// [`POLL_INTERVAL`].
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let modified = self.scan();
+        let changed = self
+            .files
+            .iter()
+            .filter(|path| modified.get(*path) != self.modified.get(*path))
+            .cloned()
+            .collect();
+        self.modified = modified;
+        changed
+    }
+
+    /// Modification times of the watched files that exist.
+    fn scan(&self) -> HashMap<PathBuf, SystemTime> {
+        let mut modified = HashMap::new();
+        for path in &self.files {
+            match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+                Ok(time) => {
+                    modified.insert(path.clone(), time);
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => debug!("can not stat {}: {e}", path.display()),
+            }
+        }
+        modified
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use vulkano::shader::{EntryPoint, SpecializationConstant};
+
+/// Specialization constant values by constant ID.
+///
+/// Ordered and hashable, so it can key the pipelines built with it.
+pub type Specialization = BTreeMap<u32, SpecializationValue>;
+
+/// A specialization constant value as written in a pipeline description.
+///
+/// Converted to the type the shader declares for the constant when the shader is specialized.
+#[derive(Debug, Clone, Copy)]
+pub enum SpecializationValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+}
+
+impl SpecializationValue {
+    fn key(self) -> (u8, u64) {
+        match self {
+            SpecializationValue::Bool(value) => (0, value as u64),
+            SpecializationValue::Int(value) => (1, value as u64),
+            SpecializationValue::Float(value) => (2, value.to_bits()),
+        }
+    }
+
+    /// Converts the value to the type of `declared`, the default value in the shader.
+    fn to_constant(self, declared: &SpecializationConstant) -> Option<SpecializationConstant> {
+        use SpecializationConstant as C;
+        use SpecializationValue as V;
+        Some(match (self, declared) {
+            (V::Bool(value), C::Bool(_)) => C::Bool(value),
+            (V::Int(value), C::U8(_)) => C::U8(value.try_into().ok()?),
+            (V::Int(value), C::U16(_)) => C::U16(value.try_into().ok()?),
+            (V::Int(value), C::U32(_)) => C::U32(value.try_into().ok()?),
+            (V::Int(value), C::U64(_)) => C::U64(value.try_into().ok()?),
+            (V::Int(value), C::I8(_)) => C::I8(value.try_into().ok()?),
+            (V::Int(value), C::I16(_)) => C::I16(value.try_into().ok()?),
+            (V::Int(value), C::I32(_)) => C::I32(value.try_into().ok()?),
+            (V::Int(value), C::I64(_)) => C::I64(value),
+            (V::Int(value), C::F32(_)) => C::F32(value as f32),
+            (V::Int(value), C::F64(_)) => C::F64(value as f64),
+            (V::Float(value), C::F32(_)) => C::F32(value as f32),
+            (V::Float(value), C::F64(_)) => C::F64(value),
+            _ => return None,
+        })
+    }
+}
+
+impl PartialEq for SpecializationValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SpecializationValue {}
+
+impl Hash for SpecializationValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Returns `entry_point` with the constants of `specialization` declared by its shader applied.
+///
+/// Constants the shader does not declare are ignored, so one specialization can be applied to
+/// every stage of a pipeline.
+pub fn specialize(
+    entry_point: &EntryPoint,
+    specialization: &Specialization,
+) -> Result<EntryPoint> {
+    if specialization.is_empty() {
+        return Ok(entry_point.clone());
+    }
+    let base_module = entry_point.module().base_module();
+    let declared = base_module.specialization_constants();
+    let name = &entry_point.info().name;
+
+    let specialization_info = specialization
+        .iter()
+        .filter_map(|(&id, &value)| Some((id, value, declared.get(&id)?)))
+        .map(|(id, value, declared)| {
+            let constant = value.to_constant(declared).ok_or_else(|| {
+                anyhow!(
+                    "specialization constant {id} of `{name}` is {declared:?}, \
+                     can not be set to {value:?}"
+                )
+            })?;
+            Ok((id, constant))
+        })
+        .collect::<Result<_>>()?;
+
+    base_module
+        .specialize(specialization_info)?
+        .entry_point(name)
+        .ok_or_else(|| anyhow!("no entry point `{name}` found in the specialized shader"))
+}
@@ -0,0 +1,108 @@
+use crate::config::SurfaceFormatPreference;
+use crate::vulkan::specialization::{Specialization, SpecializationValue};
+use tracing::{info, warn};
+use vulkano::format::{Format, NumericFormat};
+use vulkano::swapchain::{ColorSpace, Swapchain};
+
+/// Constant ID of `OUTPUT_TRANSFER` in `shaders/include/output.glsl`, reserved in every
+/// fragment shader.
+pub const OUTPUT_TRANSFER_CONSTANT_ID: u32 = 100;
+
+/// Transfer function fragment shaders apply to the linear colors they write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputTransfer {
+    /// Written as is: the format encodes them, or the color space is linear
+    Linear = 0,
+    /// sRGB encoded in the shader, for UNORM formats in the sRGB color space
+    Srgb = 1,
+    /// Converted to Rec. 2020 and encoded with the ST 2084 perceptual quantizer
+    Pq = 2,
+}
+
+impl OutputTransfer {
+    /// Transfer function for the images of `swapchain`.
+    pub fn of(swapchain: &Swapchain) -> Self {
+        match swapchain.image_color_space() {
+            ColorSpace::Hdr10St2084 => Self::Pq,
+            ColorSpace::ExtendedSrgbLinear => Self::Linear,
+            _ if swapchain.image_format().numeric_format_color() == Some(NumericFormat::SRGB) => {
+                Self::Linear
+            }
+            _ => Self::Srgb,
+        }
+    }
+
+    /// Specialization setting `OUTPUT_TRANSFER` to this transfer function.
+    pub fn specialization(self) -> Specialization {
+        Specialization::from([(
+            OUTPUT_TRANSFER_CONSTANT_ID,
+            SpecializationValue::Int(self as i64),
+        )])
+    }
+}
+
+const SRGB: &[(Format, ColorSpace)] = &[
+    (Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear),
+    (Format::R8G8B8A8_SRGB, ColorSpace::SrgbNonLinear),
+    (Format::B8G8R8A8_UNORM, ColorSpace::SrgbNonLinear),
+    (Format::R8G8B8A8_UNORM, ColorSpace::SrgbNonLinear),
+];
+
+const TEN_BIT: &[(Format, ColorSpace)] = &[
+    (Format::A2B10G10R10_UNORM_PACK32, ColorSpace::SrgbNonLinear),
+    (Format::A2R10G10B10_UNORM_PACK32, ColorSpace::SrgbNonLinear),
+];
+
+const HDR10: &[(Format, ColorSpace)] = &[
+    (Format::A2B10G10R10_UNORM_PACK32, ColorSpace::Hdr10St2084),
+    (Format::A2R10G10B10_UNORM_PACK32, ColorSpace::Hdr10St2084),
+];
+
+const SCRGB: &[(Format, ColorSpace)] =
+    &[(Format::R16G16B16A16_SFLOAT, ColorSpace::ExtendedSrgbLinear)];
+
+/// Groups of surface formats to try for `preference`, most preferred first.
+///
+/// HDR and 10-bit fall back to 8-bit sRGB, which every desktop driver supports.
+fn surface_format_candidates(
+    preference: SurfaceFormatPreference,
+) -> &'static [&'static [(Format, ColorSpace)]] {
+    match preference {
+        SurfaceFormatPreference::Srgb | SurfaceFormatPreference::Auto => &[SRGB],
+        SurfaceFormatPreference::TenBit => &[TEN_BIT, SRGB],
+        SurfaceFormatPreference::Hdr10 => &[HDR10, SCRGB, TEN_BIT, SRGB],
+        SurfaceFormatPreference::ScRgb => &[SCRGB, HDR10, TEN_BIT, SRGB],
+    }
+}
+
+/// Picks the most preferred of `available_formats`, or the first one if none is a candidate.
+///
+/// HDR color spaces are only reported if the instance enables `VK_EXT_swapchain_colorspace`.
+pub fn choose_surface_format(
+    available_formats: &[(Format, ColorSpace)],
+    preference: SurfaceFormatPreference,
+) -> Option<(Format, ColorSpace)> {
+    let candidates = surface_format_candidates(preference);
+    let Some((rank, surface_format)) = candidates.iter().enumerate().find_map(|(rank, group)| {
+        let found = group
+            .iter()
+            .find(|format| available_formats.contains(format))?;
+        Some((rank, *found))
+    }) else {
+        let first = available_formats.first().copied();
+        warn!(
+            "no known surface format available (available: {available_formats:?}), \
+             using {first:?}"
+        );
+        return first;
+    };
+    if rank == 0 {
+        info!("surface format {surface_format:?} ({preference:?} requested)");
+    } else {
+        warn!(
+            "{preference:?} surface formats are unavailable (available: {available_formats:?}), \
+             falling back to {surface_format:?}"
+        );
+    }
+    Some(surface_format)
+}
@@ -1,7 +1,10 @@
-use crate::app_error::AppError;
+use crate::app_error::{AppError, DeviceRejection};
+use crate::config::{AppConfig, FramePacing, PresentModePreference};
 use crate::vulkan::queue_family_indices::QueueFamilyIndices;
+use crate::vulkan::surface_format::choose_surface_format;
 use anyhow::Result;
 use std::sync::Arc;
+use tracing::{info, warn};
 use vulkano::device::physical::PhysicalDevice;
 use vulkano::device::Device;
 use vulkano::format::Format;
@@ -37,9 +40,15 @@ impl SwapChainSupportDetails {
         })
     }
 
-    #[inline]
-    pub fn is_adequate(&self) -> bool {
-        !self.formats.is_empty() && !self.present_modes.is_empty()
+    pub fn inadequacies(&self) -> Vec<DeviceRejection> {
+        let mut rejections = Vec::new();
+        if self.formats.is_empty() {
+            rejections.push(DeviceRejection::NoSurfaceFormats);
+        }
+        if self.present_modes.is_empty() {
+            rejections.push(DeviceRejection::NoPresentModes);
+        }
+        rejections
     }
 
     pub fn create_swapchain(
@@ -48,14 +57,18 @@ impl SwapChainSupportDetails {
         surface: &Arc<Surface>,
         window: &Window,
         queue_family_indices: &QueueFamilyIndices,
+        config: &AppConfig,
     ) -> Result<(Arc<Swapchain>, Vec<Arc<Image>>)> {
-        let (image_format, image_color_space) = choose_swap_surface_format(self.formats)?;
-        let present_mode = choose_swap_present_mode(self.present_modes);
+        let (image_format, image_color_space) =
+            choose_surface_format(&self.formats, config.surface_format)
+                .ok_or(AppError::SwapChainFormatUnavailable)?;
+        let present_mode = choose_swap_present_mode(&self.present_modes, config.present_mode);
         let image_extent = choose_swap_extent(&self.capabilities, window);
-        let mut min_image_count = self.capabilities.min_image_count + 1;
-        if let Some(max_image_count) = self.capabilities.max_image_count {
-            min_image_count = min_image_count.min(max_image_count);
-        }
+        let min_image_count = choose_image_count(
+            &self.capabilities,
+            config.swapchain_images,
+            config.frame_pacing,
+        );
         let image_sharing =
             if queue_family_indices.graphics_family == queue_family_indices.present_family {
                 Sharing::Exclusive
@@ -83,46 +96,114 @@ impl SwapChainSupportDetails {
             clipped: true,
             ..SwapchainCreateInfo::default()
         };
-        Ok(Swapchain::new(
-            device.clone(),
-            surface.clone(),
-            swapchain_create_info,
-        )?)
+        let (swapchain, images) =
+            Swapchain::new(device.clone(), surface.clone(), swapchain_create_info)?;
+        info!(
+            "swapchain images: {min_image_count} requested, {} granted",
+            images.len()
+        );
+        Ok((swapchain, images))
     }
 }
 
-fn choose_swap_surface_format(
-    available_formats: impl IntoIterator<Item = (Format, ColorSpace)>,
-) -> Result<(Format, ColorSpace)> {
-    let mut iter = available_formats.into_iter();
-    let first = iter.next();
-    Ok(first
-        .iter()
-        .copied()
-        .chain(iter)
-        .find(|format| format == &(Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear))
-        .or(first)
-        .ok_or(AppError::SwapChainFormatUnavailable)?)
+/// Present modes to try for `preference`, most preferred first, with why each fallback is taken.
+///
+/// Every chain ends with fifo, the only mode every device supports.
+fn present_mode_candidates(
+    preference: PresentModePreference,
+) -> &'static [(PresentMode, &'static str)] {
+    match preference {
+        PresentModePreference::Fifo => &[(PresentMode::Fifo, "")],
+        PresentModePreference::FifoRelaxed => &[
+            (PresentMode::FifoRelaxed, ""),
+            (
+                PresentMode::Fifo,
+                "late frames wait for the next vertical blank",
+            ),
+        ],
+        PresentModePreference::Mailbox | PresentModePreference::Auto => &[
+            (PresentMode::Mailbox, ""),
+            (
+                PresentMode::Fifo,
+                "frames are synchronized with the display, adding latency",
+            ),
+        ],
+        PresentModePreference::Immediate => &[
+            (PresentMode::Immediate, ""),
+            (
+                PresentMode::Mailbox,
+                "frames do not tear, but unpresented ones are dropped",
+            ),
+            (
+                PresentMode::Fifo,
+                "frames are synchronized with the display",
+            ),
+        ],
+    }
 }
 
 fn choose_swap_present_mode(
-    available_present_modes: impl IntoIterator<Item = PresentMode>,
+    available_present_modes: &[PresentMode],
+    preference: PresentModePreference,
 ) -> PresentMode {
-    available_present_modes
-        .into_iter()
-        .find(|&mode| mode == PresentMode::Mailbox)
-        .unwrap_or(PresentMode::Fifo)
+    let candidates = present_mode_candidates(preference);
+    let (present_mode, reason) = candidates
+        .iter()
+        .find(|(mode, _)| available_present_modes.contains(mode))
+        .copied()
+        .unwrap_or((PresentMode::Fifo, ""));
+    if present_mode == candidates[0].0 {
+        info!("present mode {present_mode:?} ({preference:?} requested)");
+    } else if preference == PresentModePreference::Auto {
+        info!("present mode {present_mode:?}, mailbox is unavailable");
+    } else {
+        warn!(
+            "present mode {:?} is unavailable (available: {available_present_modes:?}), \
+             falling back to {present_mode:?}: {reason}",
+            candidates[0].0
+        );
+    }
+    present_mode
 }
 
-fn choose_swap_extent(surface_capabilities: &SurfaceCapabilities, window: &Window) -> [u32; 2] {
-    if let Some(current_extent) = surface_capabilities.current_extent {
-        current_extent
-    } else {
-        let [x, y]: [u32; 2] = window.inner_size().into();
-        let [min_x, min_y] = surface_capabilities.min_image_extent;
-        let [max_x, max_y] = surface_capabilities.max_image_extent;
-        [x.clamp(min_x, max_x), y.clamp(min_y, max_y)]
+/// Number of swapchain images to request: `requested` if set, otherwise what `frame_pacing`
+/// calls for, clamped to what the surface supports.
+fn choose_image_count(
+    capabilities: &SurfaceCapabilities,
+    requested: Option<u32>,
+    frame_pacing: FramePacing,
+) -> u32 {
+    let preferred = requested.unwrap_or(match frame_pacing {
+        // Double buffering, the CPU waits on the display sooner
+        FramePacing::Latency => capabilities.min_image_count.max(2),
+        // Triple buffering, the CPU records a frame while two are queued
+        FramePacing::Throughput => (capabilities.min_image_count + 1).max(3),
+    });
+    let max_image_count = capabilities.max_image_count.unwrap_or(u32::MAX);
+    let image_count = preferred.clamp(capabilities.min_image_count, max_image_count);
+    if image_count != preferred {
+        warn!(
+            "surface supports {} to {max_image_count} swapchain images, requesting {image_count} \
+             instead of {preferred}",
+            capabilities.min_image_count
+        );
     }
+    image_count
+}
+
+/// The extent the surface requires, or else the window size, clamped to the extents the surface
+/// supports.
+///
+/// The current extent is clamped too: during an interactive resize it can already be outside the
+/// range reported with it.
+pub fn choose_swap_extent(surface_capabilities: &SurfaceCapabilities, window: &Window) -> [u32; 2] {
+    let [x, y] = surface_capabilities
+        .current_extent
+        .unwrap_or_else(|| window.inner_size().into());
+    let [min_x, min_y] = surface_capabilities.min_image_extent;
+    let [max_x, max_y] = surface_capabilities.max_image_extent;
+    // Not `clamp`, which panics if a driver reports a minimum above the maximum
+    [x.max(min_x).min(max_x), y.max(min_y).min(max_y)]
 }
 
 pub fn create_image_views(swapchain_images: &[Arc<Image>]) -> Result<Vec<Arc<ImageView>>> {
@@ -1,10 +1,10 @@
+use crate::config::{FullscreenMode, VideoModeRequest, WindowConfig};
 use anyhow::Result;
-use winit::dpi::PhysicalSize;
+use tracing::{debug, info, warn};
+use winit::dpi::{LogicalSize, PhysicalPosition};
 use winit::event_loop::EventLoop;
-use winit::window::{Window, WindowBuilder};
-
-const WIDTH: i32 = 800;
-const HEIGHT: i32 = 600;
+use winit::monitor::{MonitorHandle, VideoMode};
+use winit::window::{Fullscreen, Window, WindowBuilder};
 
 pub struct AppWindow {
     pub event_loop: EventLoop<()>,
@@ -12,15 +12,100 @@ pub struct AppWindow {
 }
 
 impl AppWindow {
-    pub fn init() -> Result<Self> {
+    pub fn init(config: &WindowConfig) -> Result<Self> {
         let event_loop = EventLoop::new();
 
-        let window = WindowBuilder::new()
-            .with_resizable(false)
-            .build(&event_loop)?;
-        window.set_inner_size(PhysicalSize::new(WIDTH, HEIGHT));
-        window.set_title("Vulkan Tutorial");
+        let mut builder = WindowBuilder::new()
+            .with_inner_size(LogicalSize::new(config.width, config.height))
+            .with_title(&config.title)
+            .with_resizable(config.resizable)
+            .with_maximized(config.maximized)
+            .with_decorations(config.decorations);
+        if let Some([x, y]) = config.position {
+            builder = builder.with_position(PhysicalPosition::new(x, y));
+        }
+        let window = builder.build(&event_loop)?;
+        info!(
+            "window inner size {:?} at scale factor {}",
+            window.inner_size(),
+            window.scale_factor()
+        );
+        if config.fullscreen != FullscreenMode::Windowed {
+            set_fullscreen(&window, config.fullscreen, config.video_mode);
+        }
 
         Ok(Self { event_loop, window })
     }
 }
+
+/// Switches `window` to `mode` on the monitor it is on.
+///
+/// Exclusive fullscreen falls back to borderless if the monitor reports no video modes.
+pub fn set_fullscreen(window: &Window, mode: FullscreenMode, video_mode: Option<VideoModeRequest>) {
+    let monitor = window.current_monitor();
+    let fullscreen = match mode {
+        FullscreenMode::Windowed => None,
+        FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+        FullscreenMode::Exclusive => {
+            match monitor
+                .as_ref()
+                .and_then(|m| choose_video_mode(m, video_mode))
+            {
+                Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                None => {
+                    warn!("no video modes for exclusive fullscreen, falling back to borderless");
+                    Some(Fullscreen::Borderless(monitor))
+                }
+            }
+        }
+    };
+    info!("switching to {mode:?}");
+    window.set_fullscreen(fullscreen);
+}
+
+/// The video mode of `monitor` matching `request`, or its largest one with the highest refresh
+/// rate.
+fn choose_video_mode(
+    monitor: &MonitorHandle,
+    request: Option<VideoModeRequest>,
+) -> Option<VideoMode> {
+    let video_modes: Vec<_> = monitor.video_modes().collect();
+    let name = monitor.name().unwrap_or_default();
+    debug!("video modes of monitor {name}: {video_modes:?}");
+    let refresh_rate = |video_mode: &VideoMode| (video_mode.refresh_rate_millihertz() + 500) / 1000;
+    let rank = |video_mode: &&VideoMode| {
+        let size = video_mode.size();
+        (
+            u64::from(size.width) * u64::from(size.height),
+            video_mode.refresh_rate_millihertz(),
+            video_mode.bit_depth(),
+        )
+    };
+
+    let matching = request.and_then(|request| {
+        video_modes
+            .iter()
+            .filter(|video_mode| {
+                let size = video_mode.size();
+                size.width == request.width
+                    && size.height == request.height
+                    && request
+                        .refresh_rate
+                        .is_none_or(|hz| refresh_rate(video_mode) == hz)
+            })
+            .max_by_key(rank)
+    });
+    if let (Some(request), None) = (request, matching) {
+        warn!("video mode {request} is unavailable on monitor {name}, using the largest one");
+    }
+    let video_mode = matching.or_else(|| video_modes.iter().max_by_key(rank))?;
+    let size = video_mode.size();
+    info!(
+        "video mode {}x{}@{} with {}-bit color on monitor {name}",
+        size.width,
+        size.height,
+        refresh_rate(video_mode),
+        video_mode.bit_depth()
+    );
+    Some(video_mode.clone())
+}